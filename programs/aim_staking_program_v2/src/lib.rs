@@ -1,13 +1,45 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token_interface::{self as token_interface, Mint, TokenAccount, TokenInterface, Transfer};
+use anchor_spl::token_interface::{self as token_interface, CloseAccount, Mint, TokenAccount, TokenInterface, Transfer, TransferChecked};
 
 declare_id!("BGWDziWKGkAFPjArzYYQfU7dug5VxACKxEMDZFEMPYuN");
 
+/// Fixed-point scale applied to `RewardStream.acc_reward_per_share`.
+pub const REWARD_PRECISION: u128 = 1_000_000_000_000;
+/// The maximum number of concurrent reward streams a project can have.
+pub const MAX_REWARD_STREAMS: usize = 3;
+/// Remaining accounts passed per reward stream when rewards are paid out:
+/// the stream's reward vault, the user's reward token account, the reward mint and the stream's token program.
+pub const REWARD_ACCOUNTS_PER_STREAM: usize = 4;
+/// Role bit allowing to add and remove authorities and to change their roles.
+pub const ROLE_SUPER_ADMIN: u8 = 1 << 0;
+/// Role bit allowing to register projects and manage their durations, lock rules and reward streams.
+pub const ROLE_PROJECT_MANAGER: u8 = 1 << 1;
+/// Role bit allowing to change project fees and fee wallets.
+pub const ROLE_FEE_MANAGER: u8 = 1 << 2;
+/// Role bit allowing to pause and resume the platform and projects.
+pub const ROLE_PAUSER: u8 = 1 << 3;
+/// All role bits.
+pub const ALL_ROLES: u8 = ROLE_SUPER_ADMIN | ROLE_PROJECT_MANAGER | ROLE_FEE_MANAGER | ROLE_PAUSER;
+/// The maximum number of approvals a proposal can collect, which also caps the approval threshold.
+pub const MAX_PROPOSAL_APPROVALS: usize = 10;
+/// How long an authority nomination or a project authority transfer can be accepted (7 days).
+pub const NOMINATION_EXPIRY_SECONDS: i64 = 7 * 24 * 60 * 60;
+/// The delay between queuing a fee or fee wallet change and when it can be applied (2 days).
+pub const CONFIG_TIMELOCK_SECONDS: i64 = 2 * 24 * 60 * 60;
+/// The longest unbonding cooldown a project can set (30 days).
+pub const MAX_COOLDOWN_SECONDS: i64 = 30 * 24 * 60 * 60;
+/// Basis points denominator; a multiplier of `BPS_DENOMINATOR` is 1x.
+pub const BPS_DENOMINATOR: u64 = 10_000;
+/// The largest reward multiplier a duration can carry (5x).
+pub const MAX_MULTIPLIER_BPS: u16 = 50_000;
+
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Copy)]
 pub enum StakeStatus {
     Active,
     Unstaked,
     EmergencyUnstaked,
+    /// The unstake was requested and the tokens are waiting out the project's cooldown.
+    Cooling,
 }
 
 impl Default for StakeStatus {
@@ -16,12 +48,162 @@ impl Default for StakeStatus {
     }
 }
 
+/// A platform authority and the roles it holds.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Copy)]
+pub struct PlatformAuthority {
+    /// The authority's public key.
+    pub authority: Pubkey,
+    /// A bitmask of `ROLE_*` bits.
+    pub roles: u8,
+}
+
+impl PlatformAuthority {
+    pub const LEN: usize = 32 + 1;
+}
+
+/// A key nominated as platform authority, waiting for it to accept.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Copy)]
+pub struct Nomination {
+    /// The nominated public key.
+    pub nominee: Pubkey,
+    /// The `ROLE_*` bits the nominee gets on acceptance.
+    pub roles: u8,
+    /// The Unix timestamp after which the nomination can no longer be accepted.
+    pub expires_at: i64,
+}
+
+impl Nomination {
+    pub const LEN: usize = 32 + 1 + 8;
+}
+
+/// A sensitive administrative action that needs `PlatformConfig.approval_threshold` approvals.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Copy)]
+pub enum ProposalAction {
+    /// Nominates a platform authority with the given roles, like `nominate_authority`.
+    NominateAuthority { authority: Pubkey, roles: u8 },
+    /// Removes a platform authority.
+    RemoveAuthority { authority: Pubkey },
+    /// Replaces the roles of a platform authority.
+    SetAuthorityRoles { authority: Pubkey, roles: u8 },
+    /// Changes the approval threshold.
+    SetApprovalThreshold { threshold: u8 },
+    /// Changes a project's fee wallet and fees, like `update_project_config`.
+    UpdateProjectConfig {
+        project_config: Pubkey,
+        fee_wallet: Pubkey,
+        unstake_fee_bps: u16,
+        emergency_unstake_fee_bps: u16,
+    },
+    /// Changes a project's emergency fee redistribution, like `set_fee_redistribution`.
+    SetFeeRedistribution {
+        project_config: Pubkey,
+        emergency_fee_redistribution_bps: u16,
+    },
+    /// Hands a project to a new authority right away, like `reassign_project_authority`.
+    ReassignProjectAuthority {
+        project_config: Pubkey,
+        new_authority: Pubkey,
+    },
+    /// Changes the fee mode of a project's new stakes, like `set_fee_mode`.
+    SetFeeMode { project_config: Pubkey, fee_mode: FeeMode },
+}
+
+impl ProposalAction {
+    /// The serialized size of the largest variant.
+    pub const LEN: usize = 1 + 32 + 32 + 2 + 2;
+
+    /// Returns the role needed to propose and approve the action.
+    pub fn required_role(&self) -> u8 {
+        match self {
+            ProposalAction::NominateAuthority { .. }
+            | ProposalAction::RemoveAuthority { .. }
+            | ProposalAction::SetAuthorityRoles { .. }
+            | ProposalAction::SetApprovalThreshold { .. }
+            | ProposalAction::ReassignProjectAuthority { .. } => ROLE_SUPER_ADMIN,
+            ProposalAction::UpdateProjectConfig { .. }
+            | ProposalAction::SetFeeRedistribution { .. }
+            | ProposalAction::SetFeeMode { .. } => ROLE_FEE_MANAGER,
+        }
+    }
+
+    /// Returns the project the action applies to, if any.
+    pub fn project_config(&self) -> Option<Pubkey> {
+        match self {
+            ProposalAction::UpdateProjectConfig { project_config, .. }
+            | ProposalAction::SetFeeRedistribution { project_config, .. }
+            | ProposalAction::ReassignProjectAuthority { project_config, .. }
+            | ProposalAction::SetFeeMode { project_config, .. } => Some(*project_config),
+            _ => None,
+        }
+    }
+}
+
+/// A fee and fee wallet change waiting out `CONFIG_TIMELOCK_SECONDS`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Copy)]
+pub struct PendingConfig {
+    /// The new wallet to receive fees.
+    pub fee_wallet: Pubkey,
+    /// The new fee in basis points for a normal unstake.
+    pub unstake_fee_bps: u16,
+    /// The new fee in basis points for an emergency unstake.
+    pub emergency_unstake_fee_bps: u16,
+    /// The Unix timestamp from which the change can be applied.
+    pub effective_at: i64,
+}
+
+impl PendingConfig {
+    pub const LEN: usize = 32 + 2 + 2 + 8;
+}
+
+/// How the lock-up of a stake restarts when tokens are added to it with `increase_stake`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Copy, Default)]
+pub enum LockRestartMode {
+    /// The whole stake is locked again for its full duration from the top-up.
+    #[default]
+    Reset,
+    /// The unlock time becomes the amount-weighted average of the current unlock time
+    /// and a full duration from the top-up.
+    WeightedAverage,
+}
+
+/// Which unstake fees apply when a stake leaves a project.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Copy, Default)]
+pub enum FeeMode {
+    /// The fees recorded on the stake when it was created.
+    #[default]
+    Snapshot,
+    /// The project's fees at the time of unstaking.
+    Current,
+}
+
+/// The lifecycle state of a project.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Copy, Default)]
+pub enum ProjectStatus {
+    /// The project accepts new stakes and top-ups.
+    #[default]
+    Active,
+    /// Existing stakes run on, but no new stakes or top-ups are accepted.
+    DepositsClosed,
+    /// The project is being wound down: no deposits, and every lock is treated as matured.
+    Sunset,
+}
+
+/// A staking duration accepted by a project and the reward multiplier it earns.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Copy)]
+pub struct AllowedDuration {
+    /// The lock-up duration in days.
+    pub duration_days: u32,
+    /// The reward weight multiplier in basis points (10000 = 1x).
+    pub multiplier_bps: u16,
+}
+
 /// # AIM Staking Program
 ///
 /// A flexible staking program on Solana built with Anchor.
 /// This program allows a platform authority to manage multiple staking projects.
 /// Each project has its own configuration for staking tokens, fees, and vaults.
-/// Users can stake tokens for various durations and receive rewards (rewards not implemented yet).
+/// Users can stake tokens for various durations and earn per-second rewards from up to
+/// `MAX_REWARD_STREAMS` reward tokens per project.
 /// It supports standard unstaking after a lock-up period and an emergency unstake option.
 #[program]
 pub mod aim_staking_program_v2 {
@@ -41,8 +223,16 @@ pub mod aim_staking_program_v2 {
     /// Returns an error if the platform is already initialized.
     pub fn initialize_platform(ctx: Context<InitializePlatform>) -> Result<()> {
         let platform_config = &mut ctx.accounts.platform_config;
-        platform_config.authorities = vec![*ctx.accounts.authority.key];
+        platform_config.authorities = vec![PlatformAuthority {
+            authority: *ctx.accounts.authority.key,
+            roles: ALL_ROLES,
+        }];
         platform_config.project_count = 0;
+        platform_config.paused = false;
+        platform_config.unstake_paused = false;
+        platform_config.approval_threshold = 1;
+        platform_config.proposal_count = 0;
+        platform_config.nominations = Vec::new();
         Ok(())
     }
 
@@ -55,26 +245,19 @@ pub mod aim_staking_program_v2 {
     ///
     /// * `ctx` - The context for this instruction.
     /// * `name` - A human-readable name for the project (max 32 characters).
+    /// * `allowed_durations` - The accepted lock-up durations and their reward multipliers.
     ///
     /// # Errors
     ///
     /// Returns `NameTooLong` if the provided name exceeds 32 characters.
-    pub fn register_project(ctx: Context<RegisterProject>, name: String, allowed_durations: Vec<u32>) -> Result<()> {
-        if !ctx.accounts.platform_config.authorities.contains(ctx.accounts.authority.key) {
-            return err!(ErrorCode::NotPlatformAuthority);
-        }
+    pub fn register_project(ctx: Context<RegisterProject>, name: String, allowed_durations: Vec<AllowedDuration>) -> Result<()> {
+        ctx.accounts
+            .platform_config
+            .check_role(ctx.accounts.authority.key, ROLE_PROJECT_MANAGER)?;
         if name.len() > 32 {
             return err!(ErrorCode::NameTooLong);
         }
-        if allowed_durations.len() > 10 {
-            return err!(ErrorCode::TooManyDurations);
-        }
-        let mut de_dup_check = allowed_durations.clone();
-        de_dup_check.sort_unstable();
-        de_dup_check.dedup();
-        if de_dup_check.len() != allowed_durations.len() {
-            return err!(ErrorCode::DuplicateDurations);
-        }
+        validate_allowed_durations(&allowed_durations)?;
         let platform_config = &mut ctx.accounts.platform_config;
         let project_config = &mut ctx.accounts.project_config;
 
@@ -87,48 +270,90 @@ pub mod aim_staking_program_v2 {
         project_config.token_program = ctx.accounts.token_program.key();
         project_config.unstake_fee_bps = 0;
         project_config.emergency_unstake_fee_bps = 0;
+        project_config.emergency_fee_redistribution_bps = 0;
         project_config.allowed_durations = allowed_durations;
+        project_config.reward_stream_count = 0;
+        project_config.reward_streams = [RewardStream::default(); MAX_REWARD_STREAMS];
+        project_config.total_weight = 0;
+        project_config.lock_restart_mode = LockRestartMode::Reset;
+        project_config.cooldown_seconds = 0;
+        project_config.paused = false;
+        project_config.unstake_paused = false;
+        project_config.pending_config = None;
+        project_config.fee_mode = FeeMode::Snapshot;
+        project_config.pending_authority = None;
+        project_config.pending_authority_expires_at = 0;
+        project_config.min_stake_amount = 0;
+        project_config.max_stake_amount = 0;
+        project_config.max_per_user = 0;
+        project_config.total_staked = 0;
+        project_config.active_stake_count = 0;
+        project_config.max_total_staked = 0;
+        project_config.stake_start_ts = None;
+        project_config.stake_end_ts = None;
+        project_config.status = ProjectStatus::Active;
+        project_config.sunset_fee_waived = false;
         
         platform_config.project_count += 1;
         Ok(())
     }
 
-    /// Adds a new authority to the platform.
+    /// Nominates a new authority for the platform.
     ///
-    /// Can only be called by an existing platform authority.
+    /// The nominee joins the platform's authorities only once it signs `accept_authority`,
+    /// within `NOMINATION_EXPIRY_SECONDS`. Nominating the same key again replaces its
+    /// nomination. Can only be called by a super admin, and only while the approval
+    /// threshold is 1; otherwise use a `ProposalAction::NominateAuthority` proposal.
     ///
     /// # Arguments
     ///
     /// * `ctx` - The context for this instruction.
-    /// * `new_authority` - The public key of the new authority to add.
-    pub fn add_authority(ctx: Context<AddAuthority>, new_authority: Pubkey) -> Result<()> {
-        if !ctx
-            .accounts
-            .platform_config
-            .authorities
-            .contains(ctx.accounts.authority.key)
-        {
-            return err!(ErrorCode::NotPlatformAuthority);
-        }
-        if ctx
-            .accounts
-            .platform_config
-            .authorities
-            .contains(&new_authority)
-        {
-            return err!(ErrorCode::AuthorityAlreadyExists);
-        }
+    /// * `nominee` - The public key of the new authority.
+    /// * `roles` - The `ROLE_*` bits granted to the new authority.
+    pub fn nominate_authority(ctx: Context<NominateAuthority>, nominee: Pubkey, roles: u8) -> Result<()> {
+        let platform_config = &mut ctx.accounts.platform_config;
+        platform_config.check_role(ctx.accounts.authority.key, ROLE_SUPER_ADMIN)?;
+        platform_config.check_no_proposal_required()?;
+        platform_config.nominate(nominee, roles, Clock::get()?.unix_timestamp)
+    }
+
+    /// Accepts a nomination and adds the signer to the platform's authorities.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context for this instruction.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NominationNotFound` if the signer has no pending nomination.
+    /// Returns `NominationExpired` if the nomination has expired.
+    pub fn accept_authority(ctx: Context<AcceptAuthority>) -> Result<()> {
         ctx.accounts
             .platform_config
-            .authorities
-            .push(new_authority);
-        Ok(())
+            .accept_nomination(ctx.accounts.nominee.key(), Clock::get()?.unix_timestamp)
+    }
+
+    /// Replaces the roles of an existing authority.
+    ///
+    /// Can only be called by a super admin, and only while the approval threshold is 1.
+    /// The last super admin cannot lose that role.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context for this instruction.
+    /// * `target_authority` - The public key of the authority to update.
+    /// * `roles` - The new `ROLE_*` bits of the authority.
+    pub fn set_authority_roles(ctx: Context<SetAuthorityRoles>, target_authority: Pubkey, roles: u8) -> Result<()> {
+        let platform_config = &mut ctx.accounts.platform_config;
+        platform_config.check_role(ctx.accounts.authority.key, ROLE_SUPER_ADMIN)?;
+        platform_config.check_no_proposal_required()?;
+        platform_config.set_authority_roles(target_authority, roles)
     }
 
     /// Removes an authority from the platform.
     ///
-    /// Can only be called by an existing platform authority.
-    /// The last authority cannot be removed.
+    /// Can only be called by a super admin, and only while the approval threshold is 1.
+    /// The last super admin cannot be removed.
     ///
     /// # Arguments
     ///
@@ -138,60 +363,190 @@ pub mod aim_staking_program_v2 {
         ctx: Context<RemoveAuthority>,
         authority_to_remove: Pubkey,
     ) -> Result<()> {
-        if !ctx
-            .accounts
+        let platform_config = &mut ctx.accounts.platform_config;
+        platform_config.check_role(ctx.accounts.authority.key, ROLE_SUPER_ADMIN)?;
+        platform_config.check_no_proposal_required()?;
+        platform_config.remove_authority(authority_to_remove)
+    }
+
+    /// Proposes a sensitive administrative action.
+    ///
+    /// The proposer must hold the role the action requires and counts as its first approval.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context for this instruction.
+    /// * `action` - The action to approve and execute.
+    pub fn propose(ctx: Context<Propose>, action: ProposalAction) -> Result<()> {
+        let clock = Clock::get()?;
+        let platform_config = &mut ctx.accounts.platform_config;
+        platform_config.check_role(ctx.accounts.proposer.key, action.required_role())?;
+
+        let proposal = &mut ctx.accounts.proposal;
+        proposal.proposal_id = platform_config.proposal_count;
+        proposal.proposer = *ctx.accounts.proposer.key;
+        proposal.action = action;
+        proposal.approvals = vec![*ctx.accounts.proposer.key];
+        proposal.created_at = clock.unix_timestamp;
+        proposal.executed = false;
+        platform_config.proposal_count += 1;
+
+        emit!(ProposalCreatedEvent {
+            proposal_id: proposal.proposal_id,
+            proposer: proposal.proposer,
+            action,
+        });
+        Ok(())
+    }
+
+    /// Approves a pending proposal.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context for this instruction.
+    /// * `_proposal_id` - The ID of the proposal (used for PDA derivation).
+    ///
+    /// # Errors
+    ///
+    /// Returns `AlreadyApproved` if the signer has already approved the proposal.
+    pub fn approve(ctx: Context<Approve>, _proposal_id: u64) -> Result<()> {
+        let proposal = &mut ctx.accounts.proposal;
+        ctx.accounts
             .platform_config
-            .authorities
-            .contains(ctx.accounts.authority.key)
-        {
-            return err!(ErrorCode::NotPlatformAuthority);
+            .check_role(ctx.accounts.approver.key, proposal.action.required_role())?;
+        if proposal.approvals.contains(ctx.accounts.approver.key) {
+            return err!(ErrorCode::AlreadyApproved);
         }
-        if ctx.accounts.platform_config.authorities.len() <= 1 {
-            return err!(ErrorCode::CannotRemoveLastAuthority);
+        if proposal.approvals.len() >= MAX_PROPOSAL_APPROVALS {
+            return err!(ErrorCode::TooManyApprovals);
         }
+        proposal.approvals.push(*ctx.accounts.approver.key);
+
+        emit!(ProposalApprovedEvent {
+            proposal_id: proposal.proposal_id,
+            approver: *ctx.accounts.approver.key,
+            approvals: proposal.approvals.len() as u8,
+        });
+        Ok(())
+    }
 
-        let authorities = &mut ctx.accounts.platform_config.authorities;
-        if let Some(pos) = authorities
+    /// Executes a proposal that has reached the approval threshold.
+    ///
+    /// Only approvals from keys that still hold the action's role are counted. Actions on a
+    /// project need that `ProjectConfig` as the `project_config` account.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context for this instruction.
+    /// * `_proposal_id` - The ID of the proposal (used for PDA derivation).
+    ///
+    /// # Errors
+    ///
+    /// Returns `ThresholdNotReached` if the proposal lacks approvals.
+    pub fn execute(ctx: Context<Execute>, _proposal_id: u64) -> Result<()> {
+        let platform_config = &mut ctx.accounts.platform_config;
+        let proposal = &mut ctx.accounts.proposal;
+        let role = proposal.action.required_role();
+        platform_config.check_role(ctx.accounts.executor.key, role)?;
+        let valid_approvals = proposal
+            .approvals
             .iter()
-            .position(|x| *x == authority_to_remove)
-        {
-            authorities.remove(pos);
-        } else {
-            return err!(ErrorCode::AuthorityNotFound);
+            .filter(|key| platform_config.check_role(key, role).is_ok())
+            .count();
+        if valid_approvals < platform_config.approval_threshold as usize {
+            return err!(ErrorCode::ThresholdNotReached);
+        }
+
+        let project_config = match proposal.action.project_config() {
+            Some(key) => {
+                let project_config = ctx
+                    .accounts
+                    .project_config
+                    .as_mut()
+                    .ok_or(ErrorCode::InvalidProposalProject)?;
+                if project_config.key() != key {
+                    return err!(ErrorCode::InvalidProposalProject);
+                }
+                Some(project_config)
+            }
+            None => None,
+        };
+        match (proposal.action, project_config) {
+            (ProposalAction::NominateAuthority { authority, roles }, _) => {
+                platform_config.nominate(authority, roles, Clock::get()?.unix_timestamp)?
+            }
+            (ProposalAction::RemoveAuthority { authority }, _) => platform_config.remove_authority(authority)?,
+            (ProposalAction::SetAuthorityRoles { authority, roles }, _) => {
+                platform_config.set_authority_roles(authority, roles)?
+            }
+            (ProposalAction::SetApprovalThreshold { threshold }, _) => platform_config.set_approval_threshold(threshold)?,
+            (
+                ProposalAction::UpdateProjectConfig {
+                    fee_wallet,
+                    unstake_fee_bps,
+                    emergency_unstake_fee_bps,
+                    ..
+                },
+                Some(project_config),
+            ) => project_config.queue_fees(
+                fee_wallet,
+                unstake_fee_bps,
+                emergency_unstake_fee_bps,
+                Clock::get()?.unix_timestamp,
+            )?,
+            (
+                ProposalAction::SetFeeRedistribution {
+                    emergency_fee_redistribution_bps,
+                    ..
+                },
+                Some(project_config),
+            ) => project_config.set_fee_redistribution(emergency_fee_redistribution_bps)?,
+            (ProposalAction::ReassignProjectAuthority { new_authority, .. }, Some(project_config)) => {
+                project_config.reassign_authority(new_authority)
+            }
+            (ProposalAction::SetFeeMode { fee_mode, .. }, Some(project_config)) => project_config.fee_mode = fee_mode,
+            _ => return err!(ErrorCode::InvalidProposalProject),
         }
+        proposal.executed = true;
 
+        emit!(ProposalExecutedEvent {
+            proposal_id: proposal.proposal_id,
+            executor: *ctx.accounts.executor.key,
+        });
         Ok(())
     }
 
     /// Updates the allowed staking durations for a project.
     ///
-    /// Can only be called by the project's authority.
+    /// Can be called by the project's authority or a platform authority with the
+    /// ProjectManager role.
     ///
     /// # Arguments
     ///
     /// * `ctx` - The context for this instruction.
-    /// * `new_durations` - The new vector of allowed durations in days.
-    pub fn update_allowed_durations(ctx: Context<UpdateAllowedDurations>, new_durations: Vec<u32>) -> Result<()> {
-        if !ctx.accounts.platform_config.authorities.contains(ctx.accounts.authority.key) {
-            return err!(ErrorCode::NotPlatformAuthority);
-        }
-        if new_durations.len() > 10 {
-            return err!(ErrorCode::TooManyDurations);
-        }
-        let mut de_dup_check = new_durations.clone();
-        de_dup_check.sort_unstable();
-        de_dup_check.dedup();
-        if de_dup_check.len() != new_durations.len() {
-            return err!(ErrorCode::DuplicateDurations);
-        }
+    /// * `new_durations` - The new allowed durations in days, each with its reward multiplier.
+    ///   Existing stakes keep the weight they were created with.
+    pub fn update_allowed_durations(ctx: Context<UpdateAllowedDurations>, new_durations: Vec<AllowedDuration>) -> Result<()> {
+        ctx.accounts.project_config.check_admin(
+            &ctx.accounts.platform_config,
+            ctx.accounts.authority.key,
+            ROLE_PROJECT_MANAGER,
+        )?;
+        validate_allowed_durations(&new_durations)?;
         ctx.accounts.project_config.allowed_durations = new_durations;
         Ok(())
     }
 
     /// Updates the configuration of an existing project.
     ///
-    /// This can only be called by the project's authority. It allows updating the
-    /// fee wallet, unstake fee, and emergency unstake fee.
+    /// This can be called by the project's authority or a platform authority with the
+    /// FeeManager role. It allows updating the
+    /// fee wallet, unstake fee, and emergency unstake fee. While the approval threshold
+    /// is above 1, use a `ProposalAction::UpdateProjectConfig` proposal instead.
+    ///
+    /// Once the project holds staked tokens, cooling stakes included, the change is queued
+    /// as `pending_config` and can only be applied with `apply_pending_config` after
+    /// `CONFIG_TIMELOCK_SECONDS`, giving stakers time to exit. A new change replaces the queued one.
     ///
     /// # Arguments
     ///
@@ -205,179 +560,275 @@ pub mod aim_staking_program_v2 {
         unstake_fee_bps: u16,
         emergency_unstake_fee_bps: u16,
     ) -> Result<()> {
-        if !ctx.accounts.platform_config.authorities.contains(ctx.accounts.authority.key) {
-            return err!(ErrorCode::NotPlatformAuthority);
-        }
-        if unstake_fee_bps > 10000 || emergency_unstake_fee_bps > 10000 {
-            return err!(ErrorCode::InvalidFeeBps);
-        }
-        let project_config = &mut ctx.accounts.project_config;
-        project_config.fee_wallet = fee_wallet;
-        project_config.unstake_fee_bps = unstake_fee_bps;
-        project_config.emergency_unstake_fee_bps = emergency_unstake_fee_bps;
-        Ok(())
+        let platform_config = &ctx.accounts.platform_config;
+        ctx.accounts
+            .project_config
+            .check_admin(platform_config, ctx.accounts.authority.key, ROLE_FEE_MANAGER)?;
+        platform_config.check_no_proposal_required()?;
+        ctx.accounts.project_config.queue_fees(
+            fee_wallet,
+            unstake_fee_bps,
+            emergency_unstake_fee_bps,
+            Clock::get()?.unix_timestamp,
+        )
     }
 
-    /// Stakes a specified amount of tokens for a user.
+    /// Applies a queued fee and fee wallet change once its timelock has passed.
     ///
-    // * This instruction transfers tokens from the user's account to the project's vault
-    // * and creates a `UserStakeInfo` account to track the stake's details.
-    // *
-    // * # Arguments
-    // *
-    // * * `ctx` - The context for this instruction.
-    // * * `amount` - The amount of tokens to stake.
-    // * * `duration_days` - The lock-up duration for the stake (e.g., 1, 7, 14, 30).
-    // * * `stake_id` - A client-generated unique ID for this stake, allowing a user to have multiple stakes.
-    // *
-    // * # Errors
-    // *
-    // * Returns `InvalidDuration` if an unsupported duration is provided.
-    pub fn stake(ctx: Context<Stake>, amount: u64, duration_days: u32, stake_id: u64) -> Result<()> {
-        if amount == 0 {
-            return err!(ErrorCode::InvalidAmount);
-        }
-        // Validate duration
-        if !ctx.accounts.project_config.allowed_durations.contains(&duration_days) {
-            return err!(ErrorCode::InvalidDuration);
+    /// Anyone can call this instruction.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context for this instruction.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NoPendingConfig` if no change is queued.
+    /// Returns `ConfigChangeNotReady` if the change is not effective yet.
+    pub fn apply_pending_config(ctx: Context<ApplyPendingConfig>) -> Result<()> {
+        let project_config = &mut ctx.accounts.project_config;
+        let pending = project_config.pending_config.ok_or(ErrorCode::NoPendingConfig)?;
+        if pending.effective_at > Clock::get()?.unix_timestamp {
+            return err!(ErrorCode::ConfigChangeNotReady);
         }
+        project_config.apply_fees(pending.fee_wallet, pending.unstake_fee_bps, pending.emergency_unstake_fee_bps);
+        Ok(())
+    }
 
-        // Transfer tokens from user to vault
-        let cpi_accounts = Transfer {
-            from: ctx.accounts.user_token_account.to_account_info(),
-            to: ctx.accounts.vault.to_account_info(),
-            authority: ctx.accounts.user.to_account_info(),
-        };
-        let cpi_program = ctx.accounts.token_program.to_account_info();
-        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-        token_interface::transfer(cpi_ctx, amount)?;
-
-        // Create stake info
-        let stake_info = &mut ctx.accounts.stake_info;
-        stake_info.user = *ctx.accounts.user.key;
-        stake_info.project_config = ctx.accounts.project_config.key();
-        stake_info.project_id = ctx.accounts.project_config.project_id;
-        stake_info.stake_id = stake_id;
-        stake_info.amount = amount;
-        stake_info.stake_timestamp = Clock::get()?.unix_timestamp;
-        stake_info.duration_days = duration_days;
-        stake_info.is_staked = true;
+    /// Sets the share of emergency unstake fees redistributed to the remaining stakers.
+    ///
+    /// The redistributed part is paid through the project's reward stream for the staked
+    /// token, so such a stream must exist for redistribution to take effect. The rest of
+    /// the fee still goes to the fee wallet. While the approval threshold is above 1,
+    /// use a `ProposalAction::SetFeeRedistribution` proposal instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context for this instruction.
+    /// * `emergency_fee_redistribution_bps` - The share of the emergency fee, in basis points, given to stakers.
+    pub fn set_fee_redistribution(
+        ctx: Context<UpdateProjectConfig>,
+        emergency_fee_redistribution_bps: u16,
+    ) -> Result<()> {
+        let platform_config = &ctx.accounts.platform_config;
+        ctx.accounts
+            .project_config
+            .check_admin(platform_config, ctx.accounts.authority.key, ROLE_FEE_MANAGER)?;
+        platform_config.check_no_proposal_required()?;
+        ctx.accounts
+            .project_config
+            .set_fee_redistribution(emergency_fee_redistribution_bps)
+    }
 
-        emit!(StakeEvent {
-            user: stake_info.user,
-            project_id: stake_info.project_id,
-            stake_id: stake_info.stake_id,
-            amount: stake_info.amount,
-            duration_days: stake_info.duration_days,
-        });
+    /// Sets how the lock-up restarts when a stake is topped up with `increase_stake`.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context for this instruction.
+    /// * `lock_restart_mode` - The new lock restart mode of the project.
+    pub fn set_lock_restart_mode(ctx: Context<UpdateProjectConfig>, lock_restart_mode: LockRestartMode) -> Result<()> {
+        ctx.accounts.project_config.check_admin(
+            &ctx.accounts.platform_config,
+            ctx.accounts.authority.key,
+            ROLE_PROJECT_MANAGER,
+        )?;
+        ctx.accounts.project_config.lock_restart_mode = lock_restart_mode;
+        Ok(())
+    }
 
+    /// Sets whether new stakes pay the fees recorded when they were created or the project's current fees.
+    ///
+    /// Each stake keeps the mode it was created with, so switching to `Current` cannot
+    /// bypass the fee timelock for existing stakes. While the approval threshold is above 1,
+    /// use a `ProposalAction::SetFeeMode` proposal instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context for this instruction.
+    /// * `fee_mode` - The new fee mode of the project.
+    pub fn set_fee_mode(ctx: Context<UpdateProjectConfig>, fee_mode: FeeMode) -> Result<()> {
+        let platform_config = &ctx.accounts.platform_config;
+        ctx.accounts
+            .project_config
+            .check_admin(platform_config, ctx.accounts.authority.key, ROLE_FEE_MANAGER)?;
+        platform_config.check_no_proposal_required()?;
+        ctx.accounts.project_config.fee_mode = fee_mode;
         Ok(())
     }
 
-    /// Unstakes tokens after the lock-up period has ended.
+    /// Sets the unbonding cooldown of a project.
     ///
-    /// This instruction checks if the lock-up duration has passed. If so, it transfers
-    /// the staked tokens back to the user, minus any applicable fees. The `UserStakeInfo`
-    /// account is closed, and the rent is refunded to the user.
+    /// With a non-zero cooldown, stakes leave through `request_unstake` and `withdraw`
+    /// instead of `unstake`. Stakes already cooling keep the cooldown they started with.
+    /// An increase only applies to new stakes; a decrease applies to every stake.
     ///
     /// # Arguments
     ///
     /// * `ctx` - The context for this instruction.
-    /// * `_stake_id` - The ID of the stake to unstake (used for PDA derivation).
+    /// * `cooldown_seconds` - The time between an unstake request and the withdrawal, in seconds.
     ///
     /// # Errors
     ///
-    /// Returns `LockupPeriodNotEnded` if the stake is still locked.
-    pub fn unstake(ctx: Context<Unstake>, _stake_id: u64) -> Result<()> {
-        let stake_info = &mut ctx.accounts.stake_info;
-        let clock = Clock::get()?;
-
-        let lockup_seconds = (stake_info.duration_days as i64) * 24 * 60 * 60;
-        if stake_info.stake_timestamp + lockup_seconds > clock.unix_timestamp {
-            return err!(ErrorCode::LockupPeriodNotEnded);
+    /// Returns `InvalidCooldown` if `cooldown_seconds` is negative or above `MAX_COOLDOWN_SECONDS`.
+    pub fn set_cooldown(ctx: Context<UpdateProjectConfig>, cooldown_seconds: i64) -> Result<()> {
+        ctx.accounts.project_config.check_admin(
+            &ctx.accounts.platform_config,
+            ctx.accounts.authority.key,
+            ROLE_PROJECT_MANAGER,
+        )?;
+        if !(0..=MAX_COOLDOWN_SECONDS).contains(&cooldown_seconds) {
+            return err!(ErrorCode::InvalidCooldown);
         }
+        ctx.accounts.project_config.cooldown_seconds = cooldown_seconds;
+        Ok(())
+    }
 
-        // Transfer tokens from vault back to user
-        let project_id_bytes = ctx.accounts.project_config.project_id.to_le_bytes();
-        let authority_seeds = &[
-            b"vault-authority".as_ref(),
-            project_id_bytes.as_ref(),
-            &[ctx.bumps.vault_authority],
-        ];
-        let signer_seeds = &[&authority_seeds[..]];
-
-        // Fee calculation
-        let fee_bps = ctx.accounts.project_config.unstake_fee_bps;
-        let fee_amount = (stake_info.amount as u128)
-            .checked_mul(fee_bps as u128).unwrap()
-            .checked_div(10000).unwrap() as u64;
-        let amount_to_user = stake_info.amount.checked_sub(fee_amount).unwrap();
-
-        // Transfer fee to fee wallet
-        if fee_amount > 0 {
-            let cpi_accounts_fee = Transfer {
-                from: ctx.accounts.vault.to_account_info(),
-                to: ctx.accounts.fee_wallet.to_account_info(),
-                authority: ctx.accounts.vault_authority.to_account_info(),
-            };
-            let cpi_program = ctx.accounts.token_program.to_account_info();
-            let cpi_ctx_fee = CpiContext::new_with_signer(cpi_program, cpi_accounts_fee, signer_seeds);
-            token_interface::transfer(cpi_ctx_fee, fee_amount)?;
+    /// Sets the stake size limits of a project. Zero disables a limit.
+    ///
+    /// The limits apply to new stakes and top-ups; existing stakes are not affected.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context for this instruction.
+    /// * `min_stake_amount` - The smallest amount a new stake can have.
+    /// * `max_stake_amount` - The largest amount a single stake can reach.
+    /// * `max_per_user` - The largest total amount a user can have staked in the project.
+    pub fn set_stake_limits(
+        ctx: Context<UpdateProjectConfig>,
+        min_stake_amount: u64,
+        max_stake_amount: u64,
+        max_per_user: u64,
+    ) -> Result<()> {
+        ctx.accounts.project_config.check_admin(
+            &ctx.accounts.platform_config,
+            ctx.accounts.authority.key,
+            ROLE_PROJECT_MANAGER,
+        )?;
+        if max_stake_amount > 0 && min_stake_amount > max_stake_amount {
+            return err!(ErrorCode::InvalidStakeLimits);
         }
+        let project_config = &mut ctx.accounts.project_config;
+        project_config.min_stake_amount = min_stake_amount;
+        project_config.max_stake_amount = max_stake_amount;
+        project_config.max_per_user = max_per_user;
+        Ok(())
+    }
 
-        // Transfer remaining tokens to user
-        let cpi_accounts = Transfer {
-            from: ctx.accounts.vault.to_account_info(),
-            to: ctx.accounts.user_token_account.to_account_info(),
-            authority: ctx.accounts.vault_authority.to_account_info(),
-        };
-        let cpi_program = ctx.accounts.token_program.to_account_info();
-        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
-        token_interface::transfer(cpi_ctx, amount_to_user)?;
-        
-        stake_info.is_staked = false;
+    /// Caps the total amount staked in a project. Zero removes the cap.
+    ///
+    /// The cap applies to new stakes and top-ups; lowering it below `total_staked` does
+    /// not affect existing stakes.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context for this instruction.
+    /// * `max_total_staked` - The new cap on `total_staked`.
+    pub fn set_max_total_staked(ctx: Context<UpdateProjectConfig>, max_total_staked: u64) -> Result<()> {
+        ctx.accounts.project_config.check_admin(
+            &ctx.accounts.platform_config,
+            ctx.accounts.authority.key,
+            ROLE_PROJECT_MANAGER,
+        )?;
+        ctx.accounts.project_config.max_total_staked = max_total_staked;
+        Ok(())
+    }
 
-        let unstake_info = &mut ctx.accounts.unstake_info;
-        unstake_info.user = stake_info.user;
-        unstake_info.project_config = stake_info.project_config;
-        unstake_info.project_id = stake_info.project_id;
-        unstake_info.stake_info = stake_info.key();
-        unstake_info.stake_id = stake_info.stake_id;
-        unstake_info.amount = stake_info.amount;
-        unstake_info.unstake_timestamp = clock.unix_timestamp;
-        unstake_info.status = StakeStatus::Unstaked;
+    /// Sets the window in which a project accepts new stakes and top-ups.
+    ///
+    /// Either bound can be left open with `None`. Unstaking is not affected.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context for this instruction.
+    /// * `stake_start_ts` - The Unix timestamp from which deposits are accepted.
+    /// * `stake_end_ts` - The Unix timestamp from which deposits are rejected.
+    pub fn set_stake_window(
+        ctx: Context<UpdateProjectConfig>,
+        stake_start_ts: Option<i64>,
+        stake_end_ts: Option<i64>,
+    ) -> Result<()> {
+        ctx.accounts.project_config.check_admin(
+            &ctx.accounts.platform_config,
+            ctx.accounts.authority.key,
+            ROLE_PROJECT_MANAGER,
+        )?;
+        if let (Some(start), Some(end)) = (stake_start_ts, stake_end_ts) {
+            if end <= start {
+                return err!(ErrorCode::InvalidStakeWindow);
+            }
+        }
+        let project_config = &mut ctx.accounts.project_config;
+        project_config.stake_start_ts = stake_start_ts;
+        project_config.stake_end_ts = stake_end_ts;
+        Ok(())
+    }
 
-        emit!(UnstakeEvent {
-            user: stake_info.user,
-            project_id: stake_info.project_id,
-            stake_id: stake_info.stake_id,
-            amount: stake_info.amount,
-        });
+    /// Moves a project through its lifecycle.
+    ///
+    /// `DepositsClosed` and `Sunset` reject new stakes and top-ups. In `Sunset` every lock
+    /// counts as matured and the cooldown is skipped, so all stakes can be unstaked right
+    /// away, without the unstake fee if `sunset_fee_waived` is set. `emergency_unstake`
+    /// stays available and charges the unstake fee instead, so stakes can leave even while
+    /// unstaking is paused. `Sunset` is final.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context for this instruction.
+    /// * `status` - The new status of the project.
+    /// * `sunset_fee_waived` - Whether unstakes are free of the unstake fee in `Sunset`.
+    pub fn set_project_status(
+        ctx: Context<UpdateProjectConfig>,
+        status: ProjectStatus,
+        sunset_fee_waived: bool,
+    ) -> Result<()> {
+        ctx.accounts.project_config.check_admin(
+            &ctx.accounts.platform_config,
+            ctx.accounts.authority.key,
+            ROLE_PROJECT_MANAGER,
+        )?;
+        let project_config = &mut ctx.accounts.project_config;
+        if project_config.status == ProjectStatus::Sunset && status != ProjectStatus::Sunset {
+            return err!(ErrorCode::ProjectSunset);
+        }
+        project_config.status = status;
+        project_config.sunset_fee_waived = sunset_fee_waived;
 
+        emit!(ProjectStatusUpdatedEvent {
+            project_id: project_config.project_id,
+            status,
+            sunset_fee_waived,
+        });
         Ok(())
     }
 
-    /// Performs an emergency unstake, allowing withdrawal before the lock-up period ends.
+    /// Moves tokens that are not user principal out of a project's vault.
     ///
-    /// This instruction allows a user to bypass the lock-up period but incurs a potentially
-    /// higher fee. It transfers the tokens back to the user (minus fees) and closes the
-    /// `UserStakeInfo` account.
+    /// Only `vault.amount - total_staked` is transferred: tokens sent to the vault directly
+    /// or left over from rounding. Rewards are held in their own reward vaults, so none are
+    /// reserved in the staking vault.
     ///
     /// # Arguments
     ///
     /// * `ctx` - The context for this instruction.
-    /// * `_stake_id` - The ID of the stake to unstake (used for PDA derivation).
-    pub fn emergency_unstake(ctx: Context<EmergencyUnstake>, _stake_id: u64) -> Result<()> {
-        let stake_info = &mut ctx.accounts.stake_info;
-        
-        // Validate lockup period has not ended
-        let clock = Clock::get()?;
-        let lockup_seconds = (stake_info.duration_days as i64) * 24 * 60 * 60;
-        if stake_info.stake_timestamp + lockup_seconds <= clock.unix_timestamp {
-            return err!(ErrorCode::LockupPeriodEnded);
+    ///
+    /// # Errors
+    ///
+    /// Returns `NoSurplus` if the vault holds no more than `total_staked`.
+    pub fn sweep_surplus(ctx: Context<SweepSurplus>) -> Result<()> {
+        ctx.accounts.project_config.check_admin(
+            &ctx.accounts.platform_config,
+            ctx.accounts.authority.key,
+            ROLE_FEE_MANAGER,
+        )?;
+        let surplus = ctx
+            .accounts
+            .vault
+            .amount
+            .saturating_sub(ctx.accounts.project_config.total_staked);
+        if surplus == 0 {
+            return err!(ErrorCode::NoSurplus);
         }
 
-        // Transfer tokens from vault back to user
         let project_id_bytes = ctx.accounts.project_config.project_id.to_le_bytes();
         let authority_seeds = &[
             b"vault-authority".as_ref(),
@@ -385,149 +836,2676 @@ pub mod aim_staking_program_v2 {
             &[ctx.bumps.vault_authority],
         ];
         let signer_seeds = &[&authority_seeds[..]];
+        transfer_from_vault(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.vault.to_account_info(),
+            ctx.accounts.token_mint.to_account_info(),
+            ctx.accounts.to.to_account_info(),
+            ctx.accounts.vault_authority.to_account_info(),
+            signer_seeds,
+            surplus,
+        )?;
 
-        // Fee calculation
-        let fee_bps = ctx.accounts.project_config.emergency_unstake_fee_bps;
-        let fee_amount = (stake_info.amount as u128)
-            .checked_mul(fee_bps as u128).unwrap()
-            .checked_div(10000).unwrap() as u64;
-        let amount_to_user = stake_info.amount.checked_sub(fee_amount).unwrap();
+        emit!(SurplusSweptEvent {
+            project_id: ctx.accounts.project_config.project_id,
+            to: ctx.accounts.to.key(),
+            amount: surplus,
+        });
+        Ok(())
+    }
 
-        // Transfer fee to fee wallet
-        if fee_amount > 0 {
-            let cpi_accounts_fee = Transfer {
-                from: ctx.accounts.vault.to_account_info(),
-                to: ctx.accounts.fee_wallet.to_account_info(),
-                authority: ctx.accounts.vault_authority.to_account_info(),
-            };
-            let cpi_program = ctx.accounts.token_program.to_account_info();
-            let cpi_ctx_fee = CpiContext::new_with_signer(cpi_program, cpi_accounts_fee, signer_seeds);
-            token_interface::transfer(cpi_ctx_fee, fee_amount)?;
+    /// Moves tokens that are not owed to stakers out of a reward stream's vault.
+    ///
+    /// The rewards accrued but not yet claimed and the rest of the stream's schedule stay
+    /// reserved; only what the vault holds beyond them is transferred. Once every stake has
+    /// left the project, cooling ones included, nothing is reserved: the stream's schedule
+    /// ends, its unclaimed rewards are written off and the whole vault is swept, which
+    /// empties the reward vaults for `close_project`.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context for this instruction.
+    /// * `stream_index` - The index of the reward stream to sweep.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidRewardStream` if `stream_index` is not an active stream.
+    /// Returns `InvalidRewardAccount` if the vault, mint or token program do not match the stream.
+    /// Returns `NoSurplus` if the vault holds no more than the reserved rewards.
+    pub fn sweep_reward_surplus(ctx: Context<SweepRewardSurplus>, stream_index: u8) -> Result<()> {
+        ctx.accounts.project_config.check_admin(
+            &ctx.accounts.platform_config,
+            ctx.accounts.authority.key,
+            ROLE_FEE_MANAGER,
+        )?;
+        let index = stream_index as usize;
+        let stream = *ctx
+            .accounts
+            .project_config
+            .active_reward_streams()
+            .get(index)
+            .ok_or(ErrorCode::InvalidRewardStream)?;
+        if ctx.accounts.reward_vault.key() != stream.vault
+            || ctx.accounts.reward_mint.key() != stream.mint
+            || ctx.accounts.reward_token_program.key() != stream.token_program
+        {
+            return err!(ErrorCode::InvalidRewardAccount);
         }
 
-        let cpi_accounts = Transfer {
-            from: ctx.accounts.vault.to_account_info(),
-            to: ctx.accounts.user_token_account.to_account_info(),
+        let clock = Clock::get()?;
+        let project_config = &mut ctx.accounts.project_config;
+        project_config.update_rewards(clock.unix_timestamp)?;
+        let is_empty = project_config.total_weight == 0 && project_config.total_staked == 0;
+        let stream = &mut project_config.reward_streams[index];
+        let reserved = if is_empty {
+            // Nobody is left to earn or claim: end the schedule and write off the rounding dust
+            stream.end_timestamp = stream.end_timestamp.min(clock.unix_timestamp);
+            stream.total_distributed = stream.total_claimed;
+            0
+        } else {
+            stream
+                .remaining_emission(clock.unix_timestamp)?
+                .checked_add(stream.outstanding_rewards())
+                .ok_or(ErrorCode::MathOverflow)?
+        };
+        let surplus = ctx.accounts.reward_vault.amount.saturating_sub(reserved);
+        if surplus == 0 {
+            return err!(ErrorCode::NoSurplus);
+        }
+
+        let project_id_bytes = project_config.project_id.to_le_bytes();
+        let authority_seeds = &[
+            b"vault-authority".as_ref(),
+            project_id_bytes.as_ref(),
+            &[ctx.bumps.vault_authority],
+        ];
+        let signer_seeds = &[&authority_seeds[..]];
+        transfer_from_vault(
+            ctx.accounts.reward_token_program.to_account_info(),
+            ctx.accounts.reward_vault.to_account_info(),
+            ctx.accounts.reward_mint.to_account_info(),
+            ctx.accounts.to.to_account_info(),
+            ctx.accounts.vault_authority.to_account_info(),
+            signer_seeds,
+            surplus,
+        )?;
+
+        emit!(RewardSurplusSweptEvent {
+            project_id: ctx.accounts.project_config.project_id,
+            reward_mint: ctx.accounts.reward_mint.key(),
+            to: ctx.accounts.to.key(),
+            amount: surplus,
+        });
+        Ok(())
+    }
+
+    /// Closes an empty project, its vault and its reward vaults, sending their rent to `receiver`.
+    ///
+    /// Every stake must have left the project, cooling ones included, and the vault must
+    /// be empty; use `sweep_surplus` for tokens sent to it directly. Every reward vault
+    /// must be empty as well. Users can still close their finished positions afterwards.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context for this instruction. The remaining accounts must hold, for
+    ///   each reward stream in order, the stream's reward vault and its token program.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProjectNotEmpty` if `total_staked` is not zero.
+    /// Returns `VaultNotEmpty` if the vault still holds tokens.
+    /// Returns `MissingRewardAccounts` if the remaining accounts do not cover every reward stream.
+    /// Returns `RewardVaultNotEmpty` if a reward vault still holds tokens.
+    pub fn close_project<'info>(ctx: Context<'_, '_, '_, 'info, CloseProject<'info>>) -> Result<()> {
+        ctx.accounts.project_config.check_admin(
+            &ctx.accounts.platform_config,
+            ctx.accounts.authority.key,
+            ROLE_PROJECT_MANAGER,
+        )?;
+
+        let project_id_bytes = ctx.accounts.project_config.project_id.to_le_bytes();
+        let authority_seeds = &[
+            b"vault-authority".as_ref(),
+            project_id_bytes.as_ref(),
+            &[ctx.bumps.vault_authority],
+        ];
+        let signer_seeds = &[&authority_seeds[..]];
+        let cpi_accounts = CloseAccount {
+            account: ctx.accounts.vault.to_account_info(),
+            destination: ctx.accounts.receiver.to_account_info(),
             authority: ctx.accounts.vault_authority.to_account_info(),
         };
         let cpi_program = ctx.accounts.token_program.to_account_info();
-        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
-        token_interface::transfer(cpi_ctx, amount_to_user)?;
+        token_interface::close_account(CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds))?;
 
-        stake_info.is_staked = false;
+        // Close the reward vaults too, so neither their tokens nor their rent are stranded
+        let streams = ctx.accounts.project_config.active_reward_streams();
+        if ctx.remaining_accounts.len() != streams.len() * 2 {
+            return err!(ErrorCode::MissingRewardAccounts);
+        }
+        for (stream, accounts) in streams.iter().zip(ctx.remaining_accounts.chunks(2)) {
+            let [reward_vault, token_program] = accounts else {
+                return err!(ErrorCode::MissingRewardAccounts);
+            };
+            if reward_vault.key() != stream.vault || token_program.key() != stream.token_program {
+                return err!(ErrorCode::InvalidRewardAccount);
+            }
+            let reward_vault_account = TokenAccount::try_deserialize(&mut &reward_vault.try_borrow_data()?[..])?;
+            if reward_vault_account.amount > 0 {
+                return err!(ErrorCode::RewardVaultNotEmpty);
+            }
+            let cpi_accounts = CloseAccount {
+                account: reward_vault.clone(),
+                destination: ctx.accounts.receiver.to_account_info(),
+                authority: ctx.accounts.vault_authority.to_account_info(),
+            };
+            token_interface::close_account(CpiContext::new_with_signer(token_program.clone(), cpi_accounts, signer_seeds))?;
+        }
 
-        let unstake_info = &mut ctx.accounts.unstake_info;
-        unstake_info.user = stake_info.user;
-        unstake_info.project_config = stake_info.project_config;
-        unstake_info.project_id = stake_info.project_id;
-        unstake_info.stake_info = stake_info.key();
-        unstake_info.stake_id = stake_info.stake_id;
-        unstake_info.amount = stake_info.amount;
-        unstake_info.unstake_timestamp = Clock::get()?.unix_timestamp;
-        unstake_info.status = StakeStatus::EmergencyUnstaked;
-        
-        emit!(EmergencyUnstakeEvent {
-            user: stake_info.user,
-            project_id: stake_info.project_id,
-            stake_id: stake_info.stake_id,
-            amount: stake_info.amount,
+        emit!(ProjectClosedEvent {
+            project_id: ctx.accounts.project_config.project_id,
+            receiver: ctx.accounts.receiver.key(),
         });
+        Ok(())
+    }
+
+    /// Starts handing a project over to a new authority.
+    ///
+    /// The transfer completes when `new_authority` signs `accept_project_authority`
+    /// within `NOMINATION_EXPIRY_SECONDS`. A new call replaces the pending authority.
+    /// Platform super admins can start a transfer for any project while the approval
+    /// threshold is 1; above it, they must use a `ProposalAction::ReassignProjectAuthority` proposal.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context for this instruction.
+    /// * `new_authority` - The proposed new authority of the project.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProposalRequired` if a platform admin other than the project authority signs
+    /// while the approval threshold is above 1.
+    pub fn transfer_project_authority(ctx: Context<UpdateProjectConfig>, new_authority: Pubkey) -> Result<()> {
+        ctx.accounts.project_config.check_admin(
+            &ctx.accounts.platform_config,
+            ctx.accounts.authority.key,
+            ROLE_SUPER_ADMIN,
+        )?;
+        if ctx.accounts.authority.key() != ctx.accounts.project_config.authority {
+            ctx.accounts.platform_config.check_no_proposal_required()?;
+        }
+        let expires_at = Clock::get()?
+            .unix_timestamp
+            .checked_add(NOMINATION_EXPIRY_SECONDS)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let project_config = &mut ctx.accounts.project_config;
+        project_config.pending_authority = Some(new_authority);
+        project_config.pending_authority_expires_at = expires_at;
 
+        emit!(ProjectAuthorityTransferStartedEvent {
+            project_id: project_config.project_id,
+            authority: project_config.authority,
+            pending_authority: new_authority,
+            expires_at,
+        });
         Ok(())
     }
-}
 
-// ============== ACCOUNTS ==============
+    /// Completes a project authority transfer. Must be signed by the pending authority.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context for this instruction.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NominationExpired` if the transfer was started more than `NOMINATION_EXPIRY_SECONDS` ago.
+    pub fn accept_project_authority(ctx: Context<AcceptProjectAuthority>) -> Result<()> {
+        let project_config = &mut ctx.accounts.project_config;
+        if Clock::get()?.unix_timestamp >= project_config.pending_authority_expires_at {
+            return err!(ErrorCode::NominationExpired);
+        }
+        project_config.reassign_authority(ctx.accounts.new_authority.key());
+        Ok(())
+    }
 
-/// Holds global configuration for the entire staking platform.
-/// There is only one of these accounts, derived from the seed "platform".
-#[account]
-pub struct PlatformConfig {
-    /// The authorities that can register new projects.
-    pub authorities: Vec<Pubkey>,
-    /// A counter for the total number of projects, used to derive unique project PDAs.
-    pub project_count: u64,
-}
+    /// Hands a project to a new authority right away, without its acceptance.
+    ///
+    /// Lets platform super admins take a project back from an owner who lost their
+    /// platform roles or their key. Any pending transfer is cancelled. While the approval
+    /// threshold is above 1, use a `ProposalAction::ReassignProjectAuthority` proposal instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context for this instruction.
+    /// * `new_authority` - The new authority of the project.
+    pub fn reassign_project_authority(ctx: Context<UpdateProjectConfig>, new_authority: Pubkey) -> Result<()> {
+        let platform_config = &ctx.accounts.platform_config;
+        platform_config.check_role(ctx.accounts.authority.key, ROLE_SUPER_ADMIN)?;
+        platform_config.check_no_proposal_required()?;
+        ctx.accounts.project_config.reassign_authority(new_authority);
+        Ok(())
+    }
 
-/// Stores the configuration for a single staking project.
-#[account]
-pub struct ProjectConfig {
-    /// A unique numerical ID for the project.
-    pub project_id: u64,
-    /// The authority that can update this project's settings (e.g., fees).
-    pub authority: Pubkey,
-    /// The mint of the token that can be staked in this project.
-    pub token_mint: Pubkey,
-    /// The token vault (a PDA) that holds all staked tokens for this project.
-    pub vault: Pubkey,
-    /// A human-readable name for the project.
-    pub name: String,
-    /// The wallet that receives fees from unstaking.
-    pub fee_wallet: Pubkey,
-    /// The token program associated with the mint (SPL Token or Token-2022).
-    pub token_program: Pubkey,
-    /// The fee in basis points (1/100th of 1%) for a normal unstake.
-    pub unstake_fee_bps: u16,
-    /// The fee in basis points for an emergency unstake.
-    pub emergency_unstake_fee_bps: u16,
-    /// A list of allowed staking durations in days. Max 10.
-    pub allowed_durations: Vec<u32>,
-}
+    /// Pauses or resumes the whole platform, or a single project.
+    ///
+    /// Without a project account the platform-wide flags are set; otherwise only the
+    /// project's, which its authority can also do. `emergency_unstake` is never paused,
+    /// so users can always exit.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context for this instruction. `project_config` is optional.
+    /// * `paused` - Whether new stakes and top-ups are blocked.
+    /// * `unstake_paused` - Whether unstaking and withdrawals are blocked.
+    pub fn set_paused(ctx: Context<SetPaused>, paused: bool, unstake_paused: bool) -> Result<()> {
+        let project_id = match ctx.accounts.project_config.as_mut() {
+            Some(project_config) => {
+                project_config.check_admin(&ctx.accounts.platform_config, ctx.accounts.authority.key, ROLE_PAUSER)?;
+                project_config.paused = paused;
+                project_config.unstake_paused = unstake_paused;
+                Some(project_config.project_id)
+            }
+            None => {
+                let platform_config = &mut ctx.accounts.platform_config;
+                platform_config.check_role(ctx.accounts.authority.key, ROLE_PAUSER)?;
+                platform_config.paused = paused;
+                platform_config.unstake_paused = unstake_paused;
+                None
+            }
+        };
 
-/// Holds the details of a single user's stake.
-/// A user can have multiple stake accounts for the same project.
-#[account]
-pub struct UserStakeInfo {
-    /// The user who owns this stake.
-    pub user: Pubkey,
-    /// A reference to the `ProjectConfig` this stake belongs to.
-    pub project_config: Pubkey,
-    /// The ID of the project this stake belongs to.
-    pub project_id: u64,
-    /// A unique identifier for this stake, provided by the client.
-    pub stake_id: u64,
-    /// The amount of tokens staked.
-    pub amount: u64,
-    /// The Unix timestamp when the stake was created.
-    pub stake_timestamp: i64,
-    /// The duration of the stake lock-up in days.
-    pub duration_days: u32,
-    /// A flag indicating if the tokens are currently staked.
-    pub is_staked: bool,
+        emit!(PauseUpdatedEvent {
+            project_id,
+            paused,
+            unstake_paused,
+        });
+        Ok(())
+    }
+
+    /// Adds a reward stream to a project.
+    ///
+    /// Creates a reward vault for `reward_mint`. The stream starts idle; use `fund_rewards`
+    /// to deposit tokens and set its schedule, and `update_reward_rate` to start emission.
+    /// Rewards are shared pro-rata between all active stakes. The reward mint may use
+    /// SPL Token or Token-2022, independently of the staked token.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context for this instruction.
+    ///
+    /// # Errors
+    ///
+    /// Returns `TooManyRewardStreams` if the project already has `MAX_REWARD_STREAMS` streams.
+    pub fn add_reward_stream(ctx: Context<AddRewardStream>) -> Result<()> {
+        ctx.accounts.project_config.check_admin(
+            &ctx.accounts.platform_config,
+            ctx.accounts.authority.key,
+            ROLE_PROJECT_MANAGER,
+        )?;
+        let project_config = &mut ctx.accounts.project_config;
+        let index = project_config.reward_stream_count as usize;
+        if index >= MAX_REWARD_STREAMS {
+            return err!(ErrorCode::TooManyRewardStreams);
+        }
+        project_config.reward_streams[index] = RewardStream {
+            mint: ctx.accounts.reward_mint.key(),
+            vault: ctx.accounts.reward_vault.key(),
+            token_program: ctx.accounts.reward_token_program.key(),
+            last_update_timestamp: Clock::get()?.unix_timestamp,
+            ..RewardStream::default()
+        };
+        project_config.reward_stream_count += 1;
+        Ok(())
+    }
+
+    /// Deposits reward tokens into a stream and sets its emission window.
+    ///
+    /// Accrued rewards are checkpointed before the window changes. The stream's current
+    /// rate must be covered by the vault over the rest of the window.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context for this instruction.
+    /// * `stream_index` - The index of the reward stream to fund.
+    /// * `amount` - The amount of reward tokens to deposit (may be zero to only reschedule).
+    /// * `start_timestamp` - The Unix timestamp at which emission starts.
+    /// * `end_timestamp` - The Unix timestamp at which emission ends.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidRewardSchedule` if the window is empty or already over.
+    /// Returns `RewardScheduleUnderfunded` if the schedule would emit more than the vault holds.
+    pub fn fund_rewards(
+        ctx: Context<FundRewards>,
+        stream_index: u8,
+        amount: u64,
+        start_timestamp: i64,
+        end_timestamp: i64,
+    ) -> Result<()> {
+        ctx.accounts.project_config.check_admin(
+            &ctx.accounts.platform_config,
+            ctx.accounts.authority.key,
+            ROLE_PROJECT_MANAGER,
+        )?;
+        let clock = Clock::get()?;
+        if end_timestamp <= start_timestamp || end_timestamp <= clock.unix_timestamp {
+            return err!(ErrorCode::InvalidRewardSchedule);
+        }
+        let index = stream_index as usize;
+        let stream = *ctx
+            .accounts
+            .project_config
+            .active_reward_streams()
+            .get(index)
+            .ok_or(ErrorCode::InvalidRewardStream)?;
+        if ctx.accounts.reward_vault.key() != stream.vault
+            || ctx.accounts.reward_mint.key() != stream.mint
+            || ctx.accounts.reward_token_program.key() != stream.token_program
+        {
+            return err!(ErrorCode::InvalidRewardAccount);
+        }
+
+        if amount > 0 {
+            let cpi_accounts = TransferChecked {
+                from: ctx.accounts.funder_token_account.to_account_info(),
+                mint: ctx.accounts.reward_mint.to_account_info(),
+                to: ctx.accounts.reward_vault.to_account_info(),
+                authority: ctx.accounts.authority.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.reward_token_program.to_account_info();
+            let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+            token_interface::transfer_checked(cpi_ctx, amount, ctx.accounts.reward_mint.decimals)?;
+            ctx.accounts.reward_vault.reload()?;
+        }
+
+        let project_config = &mut ctx.accounts.project_config;
+        project_config.update_rewards(clock.unix_timestamp)?;
+        let project_id = project_config.project_id;
+        let stream = &mut project_config.reward_streams[index];
+        stream.start_timestamp = start_timestamp;
+        stream.end_timestamp = end_timestamp;
+        stream.check_funded(clock.unix_timestamp, ctx.accounts.reward_vault.amount)?;
+
+        emit!(RewardsFundedEvent {
+            project_id,
+            reward_mint: stream.mint,
+            amount,
+            start_timestamp,
+            end_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Changes the emission rate of a reward stream.
+    ///
+    /// Rewards accrued at the old rate are checkpointed first, so past accrual is preserved.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context for this instruction.
+    /// * `stream_index` - The index of the reward stream to update.
+    /// * `reward_rate` - The new amount of reward tokens emitted per second.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RewardScheduleUnderfunded` if the new rate would emit more than the vault holds.
+    pub fn update_reward_rate(ctx: Context<UpdateRewardRate>, stream_index: u8, reward_rate: u64) -> Result<()> {
+        ctx.accounts.project_config.check_admin(
+            &ctx.accounts.platform_config,
+            ctx.accounts.authority.key,
+            ROLE_PROJECT_MANAGER,
+        )?;
+        let clock = Clock::get()?;
+        let index = stream_index as usize;
+        let project_config = &mut ctx.accounts.project_config;
+        let stream = *project_config
+            .active_reward_streams()
+            .get(index)
+            .ok_or(ErrorCode::InvalidRewardStream)?;
+        if ctx.accounts.reward_vault.key() != stream.vault {
+            return err!(ErrorCode::InvalidRewardAccount);
+        }
+
+        project_config.update_rewards(clock.unix_timestamp)?;
+        let project_id = project_config.project_id;
+        let stream = &mut project_config.reward_streams[index];
+        stream.reward_rate = reward_rate;
+        stream.check_funded(clock.unix_timestamp, ctx.accounts.reward_vault.amount)?;
+
+        emit!(RewardRateUpdatedEvent {
+            project_id,
+            reward_mint: stream.mint,
+            reward_rate,
+        });
+        Ok(())
+    }
+
+    /// Stakes a specified amount of tokens for a user.
+    ///
+    // * This instruction transfers tokens from the user's account to the project's vault
+    // * and creates a `UserStakeInfo` account to track the stake's details.
+    // *
+    // * # Arguments
+    // *
+    // * * `ctx` - The context for this instruction.
+    // * * `amount` - The amount of tokens to stake.
+    // * * `duration_days` - The lock-up duration for the stake (e.g., 1, 7, 14, 30).
+    // * * `stake_id` - A client-generated unique ID for this stake, allowing a user to have multiple stakes.
+    // * * `auto_renew` - Whether the lock-up restarts for the same duration each time it ends.
+    // *
+    // * # Errors
+    // *
+    // * Returns `InvalidDuration` if an unsupported duration is provided.
+    // * Returns `StakingPaused` if the platform or the project is paused.
+    // * Returns `StakeBelowMinimum` or `StakeAboveMaximum` if `amount` is outside the project's limits.
+    // * Returns `UserStakeCapExceeded` if the user's total stake would exceed `max_per_user`.
+    // * Returns `StakingNotStarted` or `StakingEnded` outside the project's staking window.
+    // * Returns `DepositsClosed` if the project is no longer `Active`.
+    pub fn stake(ctx: Context<Stake>, amount: u64, duration_days: u32, stake_id: u64, auto_renew: bool) -> Result<()> {
+        check_staking_allowed(&ctx.accounts.platform_config, &ctx.accounts.project_config)?;
+        ctx.accounts.project_config.check_deposits_open(Clock::get()?.unix_timestamp)?;
+        if amount == 0 {
+            return err!(ErrorCode::InvalidAmount);
+        }
+        ctx.accounts.project_config.check_stake_amount(amount)?;
+        ctx.accounts.project_config.add_staked(amount)?;
+        ctx.accounts.project_config.add_active_stake()?;
+        let user_project_info = &mut ctx.accounts.user_project_info;
+        user_project_info.user = *ctx.accounts.user.key;
+        user_project_info.project_config = ctx.accounts.project_config.key();
+        user_project_info.add_stake(amount, ctx.accounts.project_config.max_per_user)?;
+        // Validate duration
+        let Some(allowed_duration) = ctx
+            .accounts
+            .project_config
+            .allowed_durations
+            .iter()
+            .find(|d| d.duration_days == duration_days)
+            .copied()
+        else {
+            return err!(ErrorCode::InvalidDuration);
+        };
+        let weight = stake_weight(amount, allowed_duration.multiplier_bps)?;
+
+        // Transfer tokens from user to vault
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.user_token_account.to_account_info(),
+            to: ctx.accounts.vault.to_account_info(),
+            authority: ctx.accounts.user.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        token_interface::transfer(cpi_ctx, amount)?;
+
+        // Bring the reward accumulator up to date before the new weight joins the pool
+        let clock = Clock::get()?;
+        let project_config = &mut ctx.accounts.project_config;
+        project_config.update_rewards(clock.unix_timestamp)?;
+        project_config.total_weight = project_config
+            .total_weight
+            .checked_add(weight)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        // Create stake info
+        let stake_info = &mut ctx.accounts.stake_info;
+        stake_info.user = *ctx.accounts.user.key;
+        stake_info.project_config = project_config.key();
+        stake_info.project_id = project_config.project_id;
+        stake_info.stake_id = stake_id;
+        stake_info.amount = amount;
+        stake_info.stake_timestamp = clock.unix_timestamp;
+        stake_info.duration_days = duration_days;
+        stake_info.is_staked = true;
+        stake_info.weight = weight;
+        stake_info.auto_renew = auto_renew;
+        stake_info.unstake_fee_bps = project_config.unstake_fee_bps;
+        stake_info.emergency_unstake_fee_bps = project_config.emergency_unstake_fee_bps;
+        stake_info.fee_mode = project_config.fee_mode;
+        stake_info.cooldown_seconds = project_config.cooldown_seconds;
+        stake_info.checkpoint_rewards(project_config.active_reward_streams());
+
+        emit!(StakeEvent {
+            user: stake_info.user,
+            project_id: stake_info.project_id,
+            stake_id: stake_info.stake_id,
+            amount: stake_info.amount,
+            duration_days: stake_info.duration_days,
+        });
+
+        Ok(())
+    }
+
+    /// Adds tokens to an active stake instead of opening a new one.
+    ///
+    /// The stake keeps its duration and earns that duration's current multiplier on the
+    /// added tokens. Its lock-up restarts according to the project's `lock_restart_mode`.
+    /// Pending rewards are kept and can still be claimed later.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context for this instruction.
+    /// * `_stake_id` - The ID of the stake to top up (used for PDA derivation).
+    /// * `amount` - The amount of tokens to add.
+    ///
+    /// # Errors
+    ///
+    /// Returns `StakingPaused` if the platform or the project is paused.
+    /// Returns `InvalidAmount` if `amount` is zero.
+    /// Returns `InvalidDuration` if the stake's duration is no longer allowed by the project.
+    pub fn increase_stake(ctx: Context<IncreaseStake>, _stake_id: u64, amount: u64) -> Result<()> {
+        check_staking_allowed(&ctx.accounts.platform_config, &ctx.accounts.project_config)?;
+        ctx.accounts.project_config.check_deposits_open(Clock::get()?.unix_timestamp)?;
+        if amount == 0 {
+            return err!(ErrorCode::InvalidAmount);
+        }
+        let new_amount = ctx.accounts.stake_info.amount.checked_add(amount).ok_or(ErrorCode::MathOverflow)?;
+        ctx.accounts.project_config.check_stake_amount(new_amount)?;
+        ctx.accounts.project_config.add_staked(amount)?;
+        ctx.accounts
+            .user_project_info
+            .add_stake(amount, ctx.accounts.project_config.max_per_user)?;
+        let duration_days = ctx.accounts.stake_info.duration_days;
+        let Some(allowed_duration) = ctx
+            .accounts
+            .project_config
+            .allowed_durations
+            .iter()
+            .find(|d| d.duration_days == duration_days)
+            .copied()
+        else {
+            return err!(ErrorCode::InvalidDuration);
+        };
+        let added_weight = stake_weight(amount, allowed_duration.multiplier_bps)?;
+
+        let cpi_accounts = TransferChecked {
+            from: ctx.accounts.user_token_account.to_account_info(),
+            mint: ctx.accounts.token_mint.to_account_info(),
+            to: ctx.accounts.vault.to_account_info(),
+            authority: ctx.accounts.user.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        token_interface::transfer_checked(cpi_ctx, amount, ctx.accounts.token_mint.decimals)?;
+
+        let clock = Clock::get()?;
+        let project_config = &mut ctx.accounts.project_config;
+        let stake_info = &mut ctx.accounts.stake_info;
+        project_config.update_rewards(clock.unix_timestamp)?;
+        project_config.total_weight = project_config
+            .total_weight
+            .checked_add(added_weight)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let new_weight = stake_info.weight.checked_add(added_weight).ok_or(ErrorCode::MathOverflow)?;
+        stake_info.reweight(new_weight, project_config.active_reward_streams())?;
+
+        match project_config.lock_restart_mode {
+            LockRestartMode::Reset => stake_info.stake_timestamp = clock.unix_timestamp,
+            LockRestartMode::WeightedAverage => {
+                // A matured stake counts as unlocking now, so the added tokens are never locked for less
+                let current_unlock = stake_info.unlock_timestamp(clock.unix_timestamp).max(clock.unix_timestamp);
+                let full_unlock = clock.unix_timestamp + stake_info.duration_seconds();
+                let weighted_unlock = (current_unlock as i128 * stake_info.amount as i128
+                    + full_unlock as i128 * amount as i128)
+                    / new_amount as i128;
+                stake_info.stake_timestamp = weighted_unlock as i64 - stake_info.duration_seconds();
+            }
+        }
+        stake_info.amount = new_amount;
+
+        emit!(StakeIncreasedEvent {
+            user: stake_info.user,
+            project_id: stake_info.project_id,
+            stake_id: stake_info.stake_id,
+            amount,
+            total_amount: new_amount,
+            unlock_timestamp: stake_info.unlock_timestamp(clock.unix_timestamp),
+        });
+
+        Ok(())
+    }
+
+    /// Moves an active stake to a longer lock-up.
+    ///
+    /// The lock-up restarts now for `new_duration_days` and the stake's weight is
+    /// recomputed with that duration's multiplier. Pending rewards are kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context for this instruction.
+    /// * `_stake_id` - The ID of the stake to extend (used for PDA derivation).
+    /// * `new_duration_days` - The new lock-up duration. Must be in the project's allowed durations.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidDuration` if the duration is not allowed by the project.
+    /// Returns `LockNotExtended` if the new lock-up would not end after the current unlock time.
+    /// Returns `ProjectSunset` if the project is being wound down.
+    pub fn extend_lock(ctx: Context<ExtendLock>, _stake_id: u64, new_duration_days: u32) -> Result<()> {
+        if ctx.accounts.project_config.status == ProjectStatus::Sunset {
+            return err!(ErrorCode::ProjectSunset);
+        }
+        let Some(allowed_duration) = ctx
+            .accounts
+            .project_config
+            .allowed_durations
+            .iter()
+            .find(|d| d.duration_days == new_duration_days)
+            .copied()
+        else {
+            return err!(ErrorCode::InvalidDuration);
+        };
+
+        let clock = Clock::get()?;
+        let project_config = &mut ctx.accounts.project_config;
+        let stake_info = &mut ctx.accounts.stake_info;
+        let new_unlock_timestamp = clock.unix_timestamp + (new_duration_days as i64) * 24 * 60 * 60;
+        if new_unlock_timestamp <= stake_info.unlock_timestamp(clock.unix_timestamp) {
+            return err!(ErrorCode::LockNotExtended);
+        }
+
+        // Swap the stake's weight for the one earned by the new duration
+        let new_weight = stake_weight(stake_info.amount, allowed_duration.multiplier_bps)?;
+        project_config.update_rewards(clock.unix_timestamp)?;
+        project_config.total_weight = project_config
+            .total_weight
+            .checked_sub(stake_info.weight)
+            .ok_or(ErrorCode::MathOverflow)?
+            .checked_add(new_weight)
+            .ok_or(ErrorCode::MathOverflow)?;
+        stake_info.reweight(new_weight, project_config.active_reward_streams())?;
+
+        let old_duration_days = stake_info.duration_days;
+        stake_info.stake_timestamp = clock.unix_timestamp;
+        stake_info.duration_days = new_duration_days;
+
+        emit!(LockExtendedEvent {
+            user: stake_info.user,
+            project_id: stake_info.project_id,
+            stake_id: stake_info.stake_id,
+            old_duration_days,
+            new_duration_days,
+            unlock_timestamp: new_unlock_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Turns auto-renewal of a stake on or off.
+    ///
+    /// Turning it off lets the current lock-up cycle run to its end, after which the stake
+    /// can be unstaked. Turning it on for a stake whose lock-up has already ended locks it
+    /// again for its full duration from now.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context for this instruction.
+    /// * `_stake_id` - The ID of the stake to update (used for PDA derivation).
+    /// * `auto_renew` - Whether the lock-up restarts each time it ends.
+    pub fn set_auto_renew(ctx: Context<SetAutoRenew>, _stake_id: u64, auto_renew: bool) -> Result<()> {
+        let clock = Clock::get()?;
+        let stake_info = &mut ctx.accounts.stake_info;
+        let unlock_timestamp = stake_info.unlock_timestamp(clock.unix_timestamp);
+        if auto_renew && unlock_timestamp <= clock.unix_timestamp {
+            stake_info.stake_timestamp = clock.unix_timestamp;
+        } else {
+            // Pin the current cycle so the lock-up no longer depends on the flag
+            stake_info.stake_timestamp = unlock_timestamp - stake_info.duration_seconds();
+        }
+        stake_info.auto_renew = auto_renew;
+
+        emit!(AutoRenewUpdatedEvent {
+            user: stake_info.user,
+            project_id: stake_info.project_id,
+            stake_id: stake_info.stake_id,
+            auto_renew,
+            unlock_timestamp: stake_info.unlock_timestamp(clock.unix_timestamp),
+        });
+
+        Ok(())
+    }
+
+    /// Unstakes tokens after the lock-up period has ended.
+    ///
+    /// This instruction checks if the lock-up duration has passed. If so, it transfers
+    /// the staked tokens back to the user, minus any applicable fees. The `UserStakeInfo`
+    /// account is closed, and the rent is refunded to the user.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context for this instruction.
+    /// * `_stake_id` - The ID of the stake to unstake (used for PDA derivation).
+    ///
+    /// Pending rewards are paid out for every reward stream whose `REWARD_ACCOUNTS_PER_STREAM`
+    /// remaining accounts are passed. The rewards of streams left out stay owed to the stake
+    /// and can be claimed later with `claim_rewards`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `LockupPeriodNotEnded` if the stake is still locked.
+    /// Returns `CooldownRequired` if the project has an unbonding cooldown; use `request_unstake` instead.
+    /// Returns `UnstakingPaused` if unstaking is paused on the platform or the project.
+    pub fn unstake<'info>(ctx: Context<'_, '_, '_, 'info, Unstake<'info>>, _stake_id: u64) -> Result<()> {
+        check_unstaking_allowed(&ctx.accounts.platform_config, &ctx.accounts.project_config)?;
+        if ctx.accounts.project_config.cooldown_required(&ctx.accounts.stake_info) {
+            return err!(ErrorCode::CooldownRequired);
+        }
+        let stake_info = &mut ctx.accounts.stake_info;
+        let clock = Clock::get()?;
+
+        if !ctx.accounts.project_config.is_matured(stake_info, clock.unix_timestamp) {
+            return err!(ErrorCode::LockupPeriodNotEnded);
+        }
+
+        // Transfer tokens from vault back to user
+        let project_id_bytes = ctx.accounts.project_config.project_id.to_le_bytes();
+        let authority_seeds = &[
+            b"vault-authority".as_ref(),
+            project_id_bytes.as_ref(),
+            &[ctx.bumps.vault_authority],
+        ];
+        let signer_seeds = &[&authority_seeds[..]];
+
+        // Settle rewards accrued by this stake and remove its weight from the pool
+        let project_config = &mut ctx.accounts.project_config;
+        project_config.update_rewards(clock.unix_timestamp)?;
+        let payouts = stake_info.settle_rewards(project_config.active_reward_streams())?;
+        project_config.total_weight = project_config
+            .total_weight
+            .checked_sub(stake_info.weight)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let unpaid = pay_rewards(
+            project_config,
+            stake_info,
+            &payouts,
+            ctx.remaining_accounts,
+            &ctx.accounts.vault_authority.to_account_info(),
+            signer_seeds,
+        )?;
+        stake_info.leave_pool(unpaid);
+
+        // Fee calculation
+        let fee_bps = ctx.accounts.project_config.unstake_fee_for(stake_info);
+        let fee_amount = calculate_fee(stake_info.amount, fee_bps)?;
+        let amount_to_user = stake_info.amount.checked_sub(fee_amount).ok_or(ErrorCode::MathOverflow)?;
+
+        // Transfer fee to fee wallet
+        if fee_amount > 0 {
+            transfer_from_vault(
+                ctx.accounts.token_program.to_account_info(),
+                ctx.accounts.vault.to_account_info(),
+                ctx.accounts.token_mint.to_account_info(),
+                ctx.accounts.fee_wallet.to_account_info(),
+                ctx.accounts.vault_authority.to_account_info(),
+                signer_seeds,
+                fee_amount,
+            )?;
+        }
+
+        // Transfer remaining tokens to user
+        transfer_from_vault(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.vault.to_account_info(),
+            ctx.accounts.token_mint.to_account_info(),
+            ctx.accounts.user_token_account.to_account_info(),
+            ctx.accounts.vault_authority.to_account_info(),
+            signer_seeds,
+            amount_to_user,
+        )?;
+        
+        stake_info.is_staked = false;
+        ctx.accounts.user_project_info.remove_stake(stake_info.amount)?;
+        ctx.accounts.project_config.remove_staked(stake_info.amount)?;
+        ctx.accounts.project_config.remove_active_stake()?;
+
+        let unstake_info = &mut ctx.accounts.unstake_info;
+        unstake_info.user = stake_info.user;
+        unstake_info.project_config = stake_info.project_config;
+        unstake_info.project_id = stake_info.project_id;
+        unstake_info.stake_info = stake_info.key();
+        unstake_info.stake_id = stake_info.stake_id;
+        unstake_info.amount = stake_info.amount;
+        unstake_info.unstake_timestamp = clock.unix_timestamp;
+        unstake_info.status = StakeStatus::Unstaked;
+
+        emit!(UnstakeEvent {
+            user: stake_info.user,
+            project_id: stake_info.project_id,
+            stake_id: stake_info.stake_id,
+            amount: stake_info.amount,
+        });
+
+        Ok(())
+    }
+
+    /// Starts the unbonding cooldown of a stake whose lock-up has ended.
+    ///
+    /// Pending rewards are paid out the same way as in `unstake` and the stake stops
+    /// earning rewards. The tokens stay in the vault until `withdraw` is called after
+    /// the project's cooldown; meanwhile `UnstakeInfo.status` is `Cooling`.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context for this instruction.
+    /// * `_stake_id` - The ID of the stake to unstake (used for PDA derivation).
+    ///
+    /// # Errors
+    ///
+    /// Returns `LockupPeriodNotEnded` if the stake is still locked.
+    /// Returns `UnstakingPaused` if unstaking is paused on the platform or the project.
+    pub fn request_unstake<'info>(ctx: Context<'_, '_, '_, 'info, RequestUnstake<'info>>, _stake_id: u64) -> Result<()> {
+        check_unstaking_allowed(&ctx.accounts.platform_config, &ctx.accounts.project_config)?;
+        let stake_info = &mut ctx.accounts.stake_info;
+        let clock = Clock::get()?;
+
+        if !ctx.accounts.project_config.is_matured(stake_info, clock.unix_timestamp) {
+            return err!(ErrorCode::LockupPeriodNotEnded);
+        }
+
+        let project_id_bytes = ctx.accounts.project_config.project_id.to_le_bytes();
+        let authority_seeds = &[
+            b"vault-authority".as_ref(),
+            project_id_bytes.as_ref(),
+            &[ctx.bumps.vault_authority],
+        ];
+        let signer_seeds = &[&authority_seeds[..]];
+
+        // Settle rewards accrued by this stake and remove its weight from the pool
+        let project_config = &mut ctx.accounts.project_config;
+        project_config.update_rewards(clock.unix_timestamp)?;
+        let payouts = stake_info.settle_rewards(project_config.active_reward_streams())?;
+        project_config.total_weight = project_config
+            .total_weight
+            .checked_sub(stake_info.weight)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let unpaid = pay_rewards(
+            project_config,
+            stake_info,
+            &payouts,
+            ctx.remaining_accounts,
+            &ctx.accounts.vault_authority.to_account_info(),
+            signer_seeds,
+        )?;
+        stake_info.leave_pool(unpaid);
+        stake_info.is_staked = false;
+        ctx.accounts.user_project_info.remove_stake(stake_info.amount)?;
+        // The tokens stay in the vault, and in `total_staked`, until they are withdrawn
+        project_config.remove_active_stake()?;
+
+        let withdrawable_timestamp = clock
+            .unix_timestamp
+            .checked_add(project_config.cooldown_for(stake_info))
+            .ok_or(ErrorCode::MathOverflow)?;
+        let unstake_info = &mut ctx.accounts.unstake_info;
+        unstake_info.user = stake_info.user;
+        unstake_info.project_config = stake_info.project_config;
+        unstake_info.project_id = stake_info.project_id;
+        unstake_info.stake_info = stake_info.key();
+        unstake_info.stake_id = stake_info.stake_id;
+        unstake_info.amount = stake_info.amount;
+        unstake_info.unstake_timestamp = clock.unix_timestamp;
+        unstake_info.status = StakeStatus::Cooling;
+        unstake_info.withdrawable_timestamp = withdrawable_timestamp;
+
+        emit!(UnstakeRequestedEvent {
+            user: stake_info.user,
+            project_id: stake_info.project_id,
+            stake_id: stake_info.stake_id,
+            amount: stake_info.amount,
+            withdrawable_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Releases the tokens of a stake whose unbonding cooldown has ended.
+    ///
+    /// The stake's unstake fee is charged, as in `unstake`. In a `Sunset` project the
+    /// cooldown no longer has to run out.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context for this instruction.
+    /// * `_stake_id` - The ID of the stake to withdraw (used for PDA derivation).
+    ///
+    /// # Errors
+    ///
+    /// Returns `NotCooling` if no unstake was requested for the stake.
+    /// Returns `CooldownNotEnded` if the cooldown is still running.
+    /// Returns `UnstakingPaused` if unstaking is paused on the platform or the project.
+    pub fn withdraw(ctx: Context<Withdraw>, _stake_id: u64) -> Result<()> {
+        check_unstaking_allowed(&ctx.accounts.platform_config, &ctx.accounts.project_config)?;
+        let clock = Clock::get()?;
+        let unstake_info = &mut ctx.accounts.unstake_info;
+        if unstake_info.withdrawable_timestamp > clock.unix_timestamp
+            && ctx.accounts.project_config.status != ProjectStatus::Sunset
+        {
+            return err!(ErrorCode::CooldownNotEnded);
+        }
+
+        let project_id_bytes = ctx.accounts.project_config.project_id.to_le_bytes();
+        let authority_seeds = &[
+            b"vault-authority".as_ref(),
+            project_id_bytes.as_ref(),
+            &[ctx.bumps.vault_authority],
+        ];
+        let signer_seeds = &[&authority_seeds[..]];
+
+        let fee_bps = ctx.accounts.project_config.unstake_fee_for(&ctx.accounts.stake_info);
+        let fee_amount = calculate_fee(unstake_info.amount, fee_bps)?;
+        let amount_to_user = unstake_info.amount.checked_sub(fee_amount).ok_or(ErrorCode::MathOverflow)?;
+        if fee_amount > 0 {
+            transfer_from_vault(
+                ctx.accounts.token_program.to_account_info(),
+                ctx.accounts.vault.to_account_info(),
+                ctx.accounts.token_mint.to_account_info(),
+                ctx.accounts.fee_wallet.to_account_info(),
+                ctx.accounts.vault_authority.to_account_info(),
+                signer_seeds,
+                fee_amount,
+            )?;
+        }
+        transfer_from_vault(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.vault.to_account_info(),
+            ctx.accounts.token_mint.to_account_info(),
+            ctx.accounts.user_token_account.to_account_info(),
+            ctx.accounts.vault_authority.to_account_info(),
+            signer_seeds,
+            amount_to_user,
+        )?;
+
+        ctx.accounts.project_config.remove_staked(unstake_info.amount)?;
+        unstake_info.unstake_timestamp = clock.unix_timestamp;
+        unstake_info.status = StakeStatus::Unstaked;
+
+        emit!(UnstakeEvent {
+            user: unstake_info.user,
+            project_id: unstake_info.project_id,
+            stake_id: unstake_info.stake_id,
+            amount: unstake_info.amount,
+        });
+
+        Ok(())
+    }
+
+    /// Withdraws part of a stake after the lock-up period has ended.
+    ///
+    /// The unstake fee applies only to the withdrawn amount. The rest stays staked with
+    /// the same lock-up and a proportionally reduced reward weight; pending rewards are
+    /// paid out first, the same way as in `unstake`, but those of streams left out stay owed.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context for this instruction.
+    /// * `_stake_id` - The ID of the stake to withdraw from (used for PDA derivation).
+    /// * `amount` - The amount of tokens to withdraw. Must be less than the staked amount.
+    ///
+    /// # Errors
+    ///
+    /// Returns `LockupPeriodNotEnded` if the stake is still locked.
+    /// Returns `CooldownRequired` if the project has an unbonding cooldown.
+    /// Returns `UnstakingPaused` if unstaking is paused on the platform or the project.
+    /// Returns `InvalidPartialAmount` if `amount` is zero or not less than the staked amount.
+    pub fn unstake_partial<'info>(
+        ctx: Context<'_, '_, '_, 'info, UnstakePartial<'info>>,
+        _stake_id: u64,
+        amount: u64,
+    ) -> Result<()> {
+        check_unstaking_allowed(&ctx.accounts.platform_config, &ctx.accounts.project_config)?;
+        if ctx.accounts.project_config.cooldown_required(&ctx.accounts.stake_info) {
+            return err!(ErrorCode::CooldownRequired);
+        }
+        let stake_info = &mut ctx.accounts.stake_info;
+        let clock = Clock::get()?;
+
+        if !ctx.accounts.project_config.is_matured(stake_info, clock.unix_timestamp) {
+            return err!(ErrorCode::LockupPeriodNotEnded);
+        }
+        if amount == 0 || amount >= stake_info.amount {
+            return err!(ErrorCode::InvalidPartialAmount);
+        }
+
+        let project_id_bytes = ctx.accounts.project_config.project_id.to_le_bytes();
+        let authority_seeds = &[
+            b"vault-authority".as_ref(),
+            project_id_bytes.as_ref(),
+            &[ctx.bumps.vault_authority],
+        ];
+        let signer_seeds = &[&authority_seeds[..]];
+
+        // Settle rewards at the current weight, then shrink the weight with the amount
+        let project_config = &mut ctx.accounts.project_config;
+        project_config.update_rewards(clock.unix_timestamp)?;
+        let payouts = stake_info.settle_rewards(project_config.active_reward_streams())?;
+        stake_info.rewards_owed = pay_rewards(
+            project_config,
+            stake_info,
+            &payouts,
+            ctx.remaining_accounts,
+            &ctx.accounts.vault_authority.to_account_info(),
+            signer_seeds,
+        )?;
+
+        let remaining_amount = stake_info.amount.checked_sub(amount).ok_or(ErrorCode::MathOverflow)?;
+        let remaining_weight = u64::try_from(
+            (stake_info.weight as u128)
+                .checked_mul(remaining_amount as u128)
+                .ok_or(ErrorCode::MathOverflow)?
+                / stake_info.amount as u128,
+        )
+        .map_err(|_| error!(ErrorCode::MathOverflow))?;
+        project_config.total_weight = project_config
+            .total_weight
+            .checked_sub(stake_info.weight - remaining_weight)
+            .ok_or(ErrorCode::MathOverflow)?;
+        stake_info.amount = remaining_amount;
+        stake_info.weight = remaining_weight;
+        ctx.accounts.user_project_info.remove_stake(amount)?;
+        project_config.remove_staked(amount)?;
+        stake_info.checkpoint_rewards(project_config.active_reward_streams());
+
+        // Fee calculation on the withdrawn part only
+        let fee_amount = calculate_fee(amount, project_config.unstake_fee_for(stake_info))?;
+        let amount_to_user = amount.checked_sub(fee_amount).ok_or(ErrorCode::MathOverflow)?;
+
+        if fee_amount > 0 {
+            transfer_from_vault(
+                ctx.accounts.token_program.to_account_info(),
+                ctx.accounts.vault.to_account_info(),
+                ctx.accounts.token_mint.to_account_info(),
+                ctx.accounts.fee_wallet.to_account_info(),
+                ctx.accounts.vault_authority.to_account_info(),
+                signer_seeds,
+                fee_amount,
+            )?;
+        }
+        transfer_from_vault(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.vault.to_account_info(),
+            ctx.accounts.token_mint.to_account_info(),
+            ctx.accounts.user_token_account.to_account_info(),
+            ctx.accounts.vault_authority.to_account_info(),
+            signer_seeds,
+            amount_to_user,
+        )?;
+
+        emit!(PartialUnstakeEvent {
+            user: stake_info.user,
+            project_id: stake_info.project_id,
+            stake_id: stake_info.stake_id,
+            amount,
+            remaining_amount,
+        });
+
+        Ok(())
+    }
+
+    /// Performs an emergency unstake, allowing withdrawal before the lock-up period ends.
+    ///
+    /// This instruction allows a user to bypass the lock-up period but incurs a potentially
+    /// higher fee. It transfers the tokens back to the user (minus fees) and closes the
+    /// `UserStakeInfo` account.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context for this instruction.
+    /// * `_stake_id` - The ID of the stake to unstake (used for PDA derivation).
+    ///
+    /// The stake's pending rewards are forfeited and stay in the reward vaults, so no reward
+    /// mint can block this exit. If the project redistributes emergency fees,
+    /// `emergency_fee_redistribution_bps` of the fee is shared between the remaining stakers
+    /// through the reward stream paying the staked token; that stream's reward vault must then
+    /// be passed as `penalty_reward_vault`.
+    ///
+    /// Matured stakes can leave this way in a `Sunset` project or while unstaking is paused,
+    /// paying the unstake fee rather than the emergency fee.
+    pub fn emergency_unstake(ctx: Context<EmergencyUnstake>, _stake_id: u64) -> Result<()> {
+        let stake_info = &mut ctx.accounts.stake_info;
+        
+        // Validate lockup period has not ended. A matured stake leaves through `unstake`,
+        // unless the project is in `Sunset` or unstaking is paused.
+        let clock = Clock::get()?;
+        let matured = ctx.accounts.project_config.is_matured(stake_info, clock.unix_timestamp);
+        if matured
+            && ctx.accounts.project_config.status != ProjectStatus::Sunset
+            && check_unstaking_allowed(&ctx.accounts.platform_config, &ctx.accounts.project_config).is_ok()
+        {
+            return err!(ErrorCode::LockupPeriodEnded);
+        }
+
+        // Transfer tokens from vault back to user
+        let project_id_bytes = ctx.accounts.project_config.project_id.to_le_bytes();
+        let authority_seeds = &[
+            b"vault-authority".as_ref(),
+            project_id_bytes.as_ref(),
+            &[ctx.bumps.vault_authority],
+        ];
+        let signer_seeds = &[&authority_seeds[..]];
+
+        // Forfeit the rewards accrued by this stake and remove its weight from the pool
+        let project_config = &mut ctx.accounts.project_config;
+        project_config.update_rewards(clock.unix_timestamp)?;
+        let forfeited = stake_info.settle_rewards(project_config.active_reward_streams())?;
+        project_config.total_weight = project_config
+            .total_weight
+            .checked_sub(stake_info.weight)
+            .ok_or(ErrorCode::MathOverflow)?;
+        forfeit_rewards(project_config, stake_info, &forfeited);
+        stake_info.leave_pool([0; MAX_REWARD_STREAMS]);
+
+        // Fee calculation
+        let fee_bps = if matured {
+            ctx.accounts.project_config.unstake_fee_for(stake_info)
+        } else {
+            ctx.accounts.project_config.emergency_unstake_fee_for(stake_info)
+        };
+        let fee_amount = calculate_fee(stake_info.amount, fee_bps)?;
+        let amount_to_user = stake_info.amount.checked_sub(fee_amount).ok_or(ErrorCode::MathOverflow)?;
+
+        // Redistribute part of the fee to the remaining stakers through the staked token's
+        // reward stream. Without such a stream or remaining stakers, the whole fee goes to
+        // the fee wallet.
+        let project_config = &mut ctx.accounts.project_config;
+        let mut redistributed_amount = 0;
+        let penalty_stream = match project_config.penalty_reward_stream() {
+            Some(index) if project_config.emergency_fee_redistribution_bps > 0 => {
+                let reward_vault = ctx
+                    .accounts
+                    .penalty_reward_vault
+                    .as_ref()
+                    .ok_or(ErrorCode::MissingRewardAccounts)?;
+                if reward_vault.key() != project_config.reward_streams[index].vault {
+                    return err!(ErrorCode::InvalidRewardAccount);
+                }
+                Some((index, reward_vault))
+            }
+            _ => None,
+        };
+        if let Some((index, reward_vault)) = penalty_stream {
+            if project_config.total_weight > 0 {
+                redistributed_amount = (fee_amount as u128)
+                    .checked_mul(project_config.emergency_fee_redistribution_bps as u128)
+                    .ok_or(ErrorCode::MathOverflow)?
+                    .checked_div(BPS_DENOMINATOR as u128)
+                    .ok_or(ErrorCode::MathOverflow)? as u64;
+            }
+            if redistributed_amount > 0 {
+                let total_weight = project_config.total_weight;
+                project_config.reward_streams[index].distribute(redistributed_amount, total_weight)?;
+                transfer_from_vault(
+                    ctx.accounts.token_program.to_account_info(),
+                    ctx.accounts.vault.to_account_info(),
+                    ctx.accounts.token_mint.to_account_info(),
+                    reward_vault.to_account_info(),
+                    ctx.accounts.vault_authority.to_account_info(),
+                    signer_seeds,
+                    redistributed_amount,
+                )?;
+                emit!(PenaltyRedistributedEvent {
+                    project_id: project_config.project_id,
+                    stake_id: stake_info.stake_id,
+                    amount: redistributed_amount,
+                });
+            }
+        }
+        let fee_wallet_amount = fee_amount.checked_sub(redistributed_amount).ok_or(ErrorCode::MathOverflow)?;
+
+        // Transfer fee to fee wallet
+        if fee_wallet_amount > 0 {
+            transfer_from_vault(
+                ctx.accounts.token_program.to_account_info(),
+                ctx.accounts.vault.to_account_info(),
+                ctx.accounts.token_mint.to_account_info(),
+                ctx.accounts.fee_wallet.to_account_info(),
+                ctx.accounts.vault_authority.to_account_info(),
+                signer_seeds,
+                fee_wallet_amount,
+            )?;
+        }
+
+        transfer_from_vault(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.vault.to_account_info(),
+            ctx.accounts.token_mint.to_account_info(),
+            ctx.accounts.user_token_account.to_account_info(),
+            ctx.accounts.vault_authority.to_account_info(),
+            signer_seeds,
+            amount_to_user,
+        )?;
+
+        stake_info.is_staked = false;
+        ctx.accounts.user_project_info.remove_stake(stake_info.amount)?;
+        ctx.accounts.project_config.remove_staked(stake_info.amount)?;
+        ctx.accounts.project_config.remove_active_stake()?;
+
+        let unstake_info = &mut ctx.accounts.unstake_info;
+        unstake_info.user = stake_info.user;
+        unstake_info.project_config = stake_info.project_config;
+        unstake_info.project_id = stake_info.project_id;
+        unstake_info.stake_info = stake_info.key();
+        unstake_info.stake_id = stake_info.stake_id;
+        unstake_info.amount = stake_info.amount;
+        unstake_info.unstake_timestamp = Clock::get()?.unix_timestamp;
+        unstake_info.status = StakeStatus::EmergencyUnstaked;
+        
+        emit!(EmergencyUnstakeEvent {
+            user: stake_info.user,
+            project_id: stake_info.project_id,
+            stake_id: stake_info.stake_id,
+            amount: stake_info.amount,
+        });
+
+        Ok(())
+    }
+
+    /// Closes the accounts of a finished stake and refunds their rent to the user.
+    ///
+    /// Both the `UserStakeInfo` and `UnstakeInfo` accounts are closed. A `PositionClosedEvent`
+    /// carrying the final state of the stake is emitted first so indexers keep its history.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context for this instruction.
+    /// * `_stake_id` - The ID of the stake to close (used for PDA derivation).
+    ///
+    /// # Errors
+    ///
+    /// Returns `StakeStillActive` if the stake has not been unstaked.
+    /// Returns `UnstakeStillCooling` if the stake is waiting out its cooldown and has not been withdrawn.
+    /// Returns `RewardsStillOwed` if the stake has unclaimed rewards and the project still exists.
+    pub fn close_position(ctx: Context<ClosePosition>, _stake_id: u64) -> Result<()> {
+        let stake_info = &ctx.accounts.stake_info;
+        let unstake_info = &ctx.accounts.unstake_info;
+        if stake_info.rewards_owed.iter().any(|owed| *owed > 0) && !ctx.accounts.project_config.data_is_empty() {
+            return err!(ErrorCode::RewardsStillOwed);
+        }
+
+        emit!(PositionClosedEvent {
+            user: stake_info.user,
+            project_id: stake_info.project_id,
+            stake_id: stake_info.stake_id,
+            amount: stake_info.amount,
+            duration_days: stake_info.duration_days,
+            stake_timestamp: stake_info.stake_timestamp,
+            unstake_timestamp: unstake_info.unstake_timestamp,
+            status: unstake_info.status,
+            rewards_claimed: stake_info.rewards_claimed,
+        });
+
+        Ok(())
+    }
+
+    /// Closes a user's `UserProjectInfo` once nothing is staked in the project and refunds its rent.
+    ///
+    /// The account is created again on the user's next stake in the project.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context for this instruction.
+    ///
+    /// # Errors
+    ///
+    /// Returns `UserProjectNotEmpty` if the user still has tokens staked in the project.
+    pub fn close_user_project(_ctx: Context<CloseUserProject>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Claims the rewards accrued by a stake without touching its principal.
+    ///
+    /// The stake stays locked and active; only the rewards earned since the last
+    /// settlement are paid, for every reward stream of the project at once. A stake that
+    /// has left can still claim the rewards left out of its exit.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context for this instruction. The remaining accounts hold
+    ///   `REWARD_ACCOUNTS_PER_STREAM` accounts per reward stream to pay; the rewards of
+    ///   streams left out stay owed to the stake.
+    /// * `_stake_id` - The ID of the stake to claim for (used for PDA derivation).
+    ///
+    /// # Errors
+    ///
+    /// Returns `NoRewardsToClaim` if the stake has no pending rewards.
+    pub fn claim_rewards<'info>(ctx: Context<'_, '_, '_, 'info, ClaimRewards<'info>>, _stake_id: u64) -> Result<()> {
+        let clock = Clock::get()?;
+        let project_config = &mut ctx.accounts.project_config;
+        let stake_info = &mut ctx.accounts.stake_info;
+
+        project_config.update_rewards(clock.unix_timestamp)?;
+        let payouts = stake_info.settle_rewards(project_config.active_reward_streams())?;
+        if payouts.iter().all(|amount| *amount == 0) {
+            return err!(ErrorCode::NoRewardsToClaim);
+        }
+
+        let project_id_bytes = project_config.project_id.to_le_bytes();
+        let authority_seeds = &[
+            b"vault-authority".as_ref(),
+            project_id_bytes.as_ref(),
+            &[ctx.bumps.vault_authority],
+        ];
+        let signer_seeds = &[&authority_seeds[..]];
+        stake_info.rewards_owed = pay_rewards(
+            project_config,
+            stake_info,
+            &payouts,
+            ctx.remaining_accounts,
+            &ctx.accounts.vault_authority.to_account_info(),
+            signer_seeds,
+        )?;
+
+        Ok(())
+    }
+}
+
+// ============== ACCOUNTS ==============
+
+/// Holds global configuration for the entire staking platform.
+/// There is only one of these accounts, derived from the seed "platform".
+#[account]
+pub struct PlatformConfig {
+    /// The authorities that can administer the platform, each with its roles.
+    pub authorities: Vec<PlatformAuthority>,
+    /// A counter for the total number of projects, used to derive unique project PDAs.
+    pub project_count: u64,
+    /// Blocks new stakes and top-ups in every project.
+    pub paused: bool,
+    /// Blocks unstaking and withdrawals in every project. Emergency unstakes stay available.
+    pub unstake_paused: bool,
+    /// The number of approvals a proposal needs. While it is 1, sensitive actions can also
+    /// be performed directly.
+    pub approval_threshold: u8,
+    /// A counter for the total number of proposals, used to derive unique proposal PDAs.
+    pub proposal_count: u64,
+    /// Keys nominated as authorities that have not accepted yet. Expired entries are
+    /// dropped on the next nomination.
+    pub nominations: Vec<Nomination>,
+}
+
+impl PlatformConfig {
+    /// Returns the account size for a platform with the given number of authorities and nominations.
+    pub fn space(authorities_len: usize, nominations_len: usize) -> usize {
+        8 + (4 + authorities_len * PlatformAuthority::LEN) + 8 + 1 + 1 + 1 + 8 + (4 + nominations_len * Nomination::LEN)
+    }
+
+    /// Fails with `ProposalRequired` if sensitive actions need more than one approval.
+    pub fn check_no_proposal_required(&self) -> Result<()> {
+        if self.approval_threshold > 1 {
+            return err!(ErrorCode::ProposalRequired);
+        }
+        Ok(())
+    }
+
+    /// Adds an authority with the given roles.
+    pub fn add_authority(&mut self, authority: Pubkey, roles: u8) -> Result<()> {
+        validate_roles(roles)?;
+        if self.roles_of(&authority).is_some() {
+            return err!(ErrorCode::AuthorityAlreadyExists);
+        }
+        self.authorities.push(PlatformAuthority { authority, roles });
+        Ok(())
+    }
+
+    /// Records a nomination of `nominee` that expires `NOMINATION_EXPIRY_SECONDS` after `now`,
+    /// replacing any earlier one for the same key and dropping expired ones.
+    pub fn nominate(&mut self, nominee: Pubkey, roles: u8, now: i64) -> Result<()> {
+        validate_roles(roles)?;
+        if self.roles_of(&nominee).is_some() {
+            return err!(ErrorCode::AuthorityAlreadyExists);
+        }
+        let expires_at = now.checked_add(NOMINATION_EXPIRY_SECONDS).ok_or(ErrorCode::MathOverflow)?;
+        self.nominations.retain(|x| x.nominee != nominee && x.expires_at > now);
+        self.nominations.push(Nomination {
+            nominee,
+            roles,
+            expires_at,
+        });
+        emit!(AuthorityNominatedEvent {
+            nominee,
+            roles,
+            expires_at,
+        });
+        Ok(())
+    }
+
+    /// Turns the nomination of `nominee` into an authority.
+    pub fn accept_nomination(&mut self, nominee: Pubkey, now: i64) -> Result<()> {
+        let Some(pos) = self.nominations.iter().position(|x| x.nominee == nominee) else {
+            return err!(ErrorCode::NominationNotFound);
+        };
+        let nomination = self.nominations.remove(pos);
+        if nomination.expires_at <= now {
+            return err!(ErrorCode::NominationExpired);
+        }
+        self.add_authority(nominee, nomination.roles)?;
+        emit!(AuthorityAddedEvent {
+            authority: nominee,
+            roles: nomination.roles,
+        });
+        Ok(())
+    }
+
+    /// Replaces the roles of an authority, keeping enough approvers to reach the threshold.
+    pub fn set_authority_roles(&mut self, authority: Pubkey, roles: u8) -> Result<()> {
+        validate_roles(roles)?;
+        let Some(entry) = self.authorities.iter_mut().find(|x| x.authority == authority) else {
+            return err!(ErrorCode::AuthorityNotFound);
+        };
+        entry.roles = roles;
+        self.check_approvers()
+    }
+
+    /// Removes an authority, keeping enough approvers to reach the threshold.
+    pub fn remove_authority(&mut self, authority: Pubkey) -> Result<()> {
+        let Some(pos) = self.authorities.iter().position(|x| x.authority == authority) else {
+            return err!(ErrorCode::AuthorityNotFound);
+        };
+        self.authorities.remove(pos);
+        self.check_approvers()
+    }
+
+    /// Fails unless at least one super admin remains and enough holders of each proposal
+    /// role remain to approve proposals. Otherwise those proposals could never pass again.
+    fn check_approvers(&self) -> Result<()> {
+        if self.role_count(ROLE_SUPER_ADMIN) == 0 {
+            return err!(ErrorCode::CannotRemoveLastAuthority);
+        }
+        if self.approver_count() < self.approval_threshold as usize {
+            return err!(ErrorCode::ApproversBelowThreshold);
+        }
+        Ok(())
+    }
+
+    /// Changes the approval threshold. It cannot exceed the number of super admins or fee managers.
+    pub fn set_approval_threshold(&mut self, threshold: u8) -> Result<()> {
+        if threshold == 0
+            || threshold as usize > MAX_PROPOSAL_APPROVALS
+            || threshold as usize > self.approver_count()
+        {
+            return err!(ErrorCode::InvalidApprovalThreshold);
+        }
+        self.approval_threshold = threshold;
+        Ok(())
+    }
+
+    /// Returns the roles of `key`, or `None` if it is not a platform authority.
+    pub fn roles_of(&self, key: &Pubkey) -> Option<u8> {
+        self.authorities
+            .iter()
+            .find(|x| x.authority == *key)
+            .map(|x| x.roles)
+    }
+
+    /// Fails unless `key` is a platform authority holding `role`.
+    pub fn check_role(&self, key: &Pubkey, role: u8) -> Result<()> {
+        match self.roles_of(key) {
+            None => err!(ErrorCode::NotPlatformAuthority),
+            Some(roles) if roles & role == 0 => err!(ErrorCode::MissingRole),
+            Some(_) => Ok(()),
+        }
+    }
+
+    /// Returns the number of authorities holding `role`.
+    pub fn role_count(&self, role: u8) -> usize {
+        self.authorities
+            .iter()
+            .filter(|x| x.roles & role != 0)
+            .count()
+    }
+
+    /// Returns the number of holders of the scarcest role that proposals need approvals from.
+    pub fn approver_count(&self) -> usize {
+        self.role_count(ROLE_SUPER_ADMIN).min(self.role_count(ROLE_FEE_MANAGER))
+    }
+}
+
+/// Stores the configuration for a single staking project.
+#[account]
+pub struct ProjectConfig {
+    /// A unique numerical ID for the project.
+    pub project_id: u64,
+    /// The owner of the project, who can update its settings (e.g., fees). Platform
+    /// authorities with the matching role can do so as well.
+    pub authority: Pubkey,
+    /// The mint of the token that can be staked in this project.
+    pub token_mint: Pubkey,
+    /// The token vault (a PDA) that holds all staked tokens for this project.
+    pub vault: Pubkey,
+    /// A human-readable name for the project.
+    pub name: String,
+    /// The wallet that receives fees from unstaking.
+    pub fee_wallet: Pubkey,
+    /// The token program associated with the mint (SPL Token or Token-2022).
+    pub token_program: Pubkey,
+    /// The fee in basis points (1/100th of 1%) for a normal unstake.
+    pub unstake_fee_bps: u16,
+    /// The fee in basis points for an emergency unstake.
+    pub emergency_unstake_fee_bps: u16,
+    /// The share of emergency unstake fees, in basis points, redistributed to the remaining stakers.
+    pub emergency_fee_redistribution_bps: u16,
+    /// A list of allowed staking durations and their reward multipliers. Max 10.
+    pub allowed_durations: Vec<AllowedDuration>,
+    /// The number of reward streams in use; only the first `reward_stream_count` entries are active.
+    pub reward_stream_count: u8,
+    /// The project's reward streams.
+    pub reward_streams: [RewardStream; MAX_REWARD_STREAMS],
+    /// The total reward weight of all active stakes.
+    pub total_weight: u64,
+    /// How the lock-up of a stake restarts when it is topped up.
+    pub lock_restart_mode: LockRestartMode,
+    /// The unbonding cooldown between `request_unstake` and `withdraw`, in seconds.
+    /// Zero means stakes are withdrawn immediately with `unstake`.
+    pub cooldown_seconds: i64,
+    /// Blocks new stakes and top-ups in this project.
+    pub paused: bool,
+    /// Blocks unstaking and withdrawals in this project. Emergency unstakes stay available.
+    pub unstake_paused: bool,
+    /// A queued fee and fee wallet change, applied with `apply_pending_config`.
+    pub pending_config: Option<PendingConfig>,
+    /// Whether new stakes pay the fees recorded at stake time or the current fees.
+    pub fee_mode: FeeMode,
+    /// The authority a transfer was started to, until it accepts with `accept_project_authority`.
+    pub pending_authority: Option<Pubkey>,
+    /// The Unix timestamp after which the pending transfer can no longer be accepted.
+    pub pending_authority_expires_at: i64,
+    /// The smallest amount a new stake can have. Zero means no minimum.
+    pub min_stake_amount: u64,
+    /// The largest amount a single stake can reach, top-ups included. Zero means no maximum.
+    pub max_stake_amount: u64,
+    /// The largest total amount a single user can have staked. Zero means no cap.
+    pub max_per_user: u64,
+    /// The staked tokens held in the vault for users, including stakes still cooling down.
+    pub total_staked: u64,
+    /// The number of active stakes.
+    pub active_stake_count: u64,
+    /// The cap on `total_staked` for new stakes and top-ups. Zero means no cap.
+    pub max_total_staked: u64,
+    /// The Unix timestamp from which new stakes and top-ups are accepted, if any.
+    pub stake_start_ts: Option<i64>,
+    /// The Unix timestamp from which new stakes and top-ups are rejected, if any.
+    pub stake_end_ts: Option<i64>,
+    /// The lifecycle state of the project.
+    pub status: ProjectStatus,
+    /// Whether unstakes are free of the unstake fee once the project is in `Sunset`.
+    pub sunset_fee_waived: bool,
+}
+
+/// A single reward token emitted by a project.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RewardStream {
+    /// The mint of the reward token.
+    pub mint: Pubkey,
+    /// The token account (a PDA) that holds this stream's reward tokens.
+    pub vault: Pubkey,
+    /// The token program of the reward mint (SPL Token or Token-2022).
+    pub token_program: Pubkey,
+    /// The amount of reward tokens emitted per second, shared by all active stakes.
+    pub reward_rate: u64,
+    /// The Unix timestamp at which emission starts.
+    pub start_timestamp: i64,
+    /// The Unix timestamp at which emission ends.
+    pub end_timestamp: i64,
+    /// Rewards accrued per unit of stake weight since the stream started, scaled by `REWARD_PRECISION`.
+    pub acc_reward_per_share: u128,
+    /// The Unix timestamp up to which `acc_reward_per_share` has been accrued.
+    pub last_update_timestamp: i64,
+    /// The total rewards accrued to stakers so far.
+    pub total_distributed: u64,
+    /// The total rewards paid out to stakers so far.
+    pub total_claimed: u64,
+}
+
+impl RewardStream {
+    /// The serialized size of a reward stream.
+    pub const LEN: usize = 32 + 32 + 32 + 8 + 8 + 8 + 16 + 8 + 8 + 8;
+
+    /// Accrues the rewards emitted within the stream's schedule since `last_update_timestamp`.
+    pub fn update(&mut self, now: i64, total_weight: u64) -> Result<()> {
+        let from = self.last_update_timestamp.max(self.start_timestamp);
+        let to = now.min(self.end_timestamp);
+        if to > from && total_weight > 0 && self.reward_rate > 0 {
+            let emitted = (self.reward_rate as u128)
+                .checked_mul((to - from) as u128)
+                .ok_or(ErrorCode::MathOverflow)?;
+            let increment = emitted
+                .checked_mul(REWARD_PRECISION)
+                .and_then(|v| v.checked_div(total_weight as u128))
+                .ok_or(ErrorCode::MathOverflow)?;
+            self.acc_reward_per_share = self
+                .acc_reward_per_share
+                .checked_add(increment)
+                .ok_or(ErrorCode::MathOverflow)?;
+            self.total_distributed = u64::try_from(emitted)
+                .ok()
+                .and_then(|emitted| self.total_distributed.checked_add(emitted))
+                .ok_or(ErrorCode::MathOverflow)?;
+        }
+        if now > self.last_update_timestamp {
+            self.last_update_timestamp = now;
+        }
+        Ok(())
+    }
+
+    /// Shares `amount` reward tokens between the current stake weight immediately,
+    /// outside of the emission schedule.
+    pub fn distribute(&mut self, amount: u64, total_weight: u64) -> Result<()> {
+        let increment = (amount as u128)
+            .checked_mul(REWARD_PRECISION)
+            .and_then(|v| v.checked_div(total_weight as u128))
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.acc_reward_per_share = self
+            .acc_reward_per_share
+            .checked_add(increment)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.total_distributed = self
+            .total_distributed
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Returns the rewards accrued to stakers but not yet paid out.
+    pub fn outstanding_rewards(&self) -> u64 {
+        self.total_distributed.saturating_sub(self.total_claimed)
+    }
+
+    /// Returns the rewards the stream will still emit from `now` until the end of its window.
+    pub fn remaining_emission(&self, now: i64) -> Result<u64> {
+        let remaining_seconds = self.end_timestamp.saturating_sub(now.max(self.start_timestamp)).max(0);
+        self.reward_rate
+            .checked_mul(remaining_seconds as u64)
+            .ok_or(error!(ErrorCode::MathOverflow))
+    }
+
+    /// Checks that `vault_balance` covers both the outstanding rewards and the rest of the schedule.
+    pub fn check_funded(&self, now: i64, vault_balance: u64) -> Result<()> {
+        let required = self
+            .remaining_emission(now)?
+            .checked_add(self.outstanding_rewards())
+            .ok_or(ErrorCode::MathOverflow)?;
+        if required > vault_balance {
+            return err!(ErrorCode::RewardScheduleUnderfunded);
+        }
+        Ok(())
+    }
+}
+
+impl ProjectConfig {
+    /// Fails unless `key` is the project's authority or a platform authority holding `role`.
+    pub fn check_admin(&self, platform_config: &PlatformConfig, key: &Pubkey, role: u8) -> Result<()> {
+        if *key == self.authority {
+            return Ok(());
+        }
+        match platform_config.roles_of(key) {
+            None => err!(ErrorCode::NotProjectAuthority),
+            Some(roles) if roles & role == 0 => err!(ErrorCode::MissingRole),
+            Some(_) => Ok(()),
+        }
+    }
+
+    /// Makes `new_authority` the project's authority and cancels any pending transfer.
+    pub fn reassign_authority(&mut self, new_authority: Pubkey) {
+        let previous_authority = self.authority;
+        self.authority = new_authority;
+        self.pending_authority = None;
+        self.pending_authority_expires_at = 0;
+
+        emit!(ProjectAuthorityTransferredEvent {
+            project_id: self.project_id,
+            previous_authority,
+            new_authority,
+        });
+    }
+
+    /// Changes the fee wallet and the unstake and emergency unstake fees.
+    ///
+    /// Without staked tokens the change is applied right away; otherwise it is queued
+    /// until `now + CONFIG_TIMELOCK_SECONDS`. Cooling stakes count, since `withdraw`
+    /// still charges them a fee.
+    pub fn queue_fees(
+        &mut self,
+        fee_wallet: Pubkey,
+        unstake_fee_bps: u16,
+        emergency_unstake_fee_bps: u16,
+        now: i64,
+    ) -> Result<()> {
+        if unstake_fee_bps > 10000 || emergency_unstake_fee_bps > 10000 {
+            return err!(ErrorCode::InvalidFeeBps);
+        }
+        if self.total_staked == 0 {
+            self.apply_fees(fee_wallet, unstake_fee_bps, emergency_unstake_fee_bps);
+            return Ok(());
+        }
+        let effective_at = now.checked_add(CONFIG_TIMELOCK_SECONDS).ok_or(ErrorCode::MathOverflow)?;
+        self.pending_config = Some(PendingConfig {
+            fee_wallet,
+            unstake_fee_bps,
+            emergency_unstake_fee_bps,
+            effective_at,
+        });
+        emit!(ConfigChangeQueuedEvent {
+            project_id: self.project_id,
+            fee_wallet,
+            unstake_fee_bps,
+            emergency_unstake_fee_bps,
+            effective_at,
+        });
+        Ok(())
+    }
+
+    /// Sets the fee wallet and fees and clears any queued change.
+    pub fn apply_fees(&mut self, fee_wallet: Pubkey, unstake_fee_bps: u16, emergency_unstake_fee_bps: u16) {
+        self.fee_wallet = fee_wallet;
+        self.unstake_fee_bps = unstake_fee_bps;
+        self.emergency_unstake_fee_bps = emergency_unstake_fee_bps;
+        self.pending_config = None;
+        emit!(ConfigChangeAppliedEvent {
+            project_id: self.project_id,
+            fee_wallet,
+            unstake_fee_bps,
+            emergency_unstake_fee_bps,
+        });
+    }
+
+    /// Checks a stake's amount against `min_stake_amount` and `max_stake_amount`.
+    pub fn check_stake_amount(&self, amount: u64) -> Result<()> {
+        if amount < self.min_stake_amount {
+            return err!(ErrorCode::StakeBelowMinimum);
+        }
+        if self.max_stake_amount > 0 && amount > self.max_stake_amount {
+            return err!(ErrorCode::StakeAboveMaximum);
+        }
+        Ok(())
+    }
+
+    /// Fails unless the project is `Active` and `now` is within its staking window.
+    pub fn check_deposits_open(&self, now: i64) -> Result<()> {
+        if self.status != ProjectStatus::Active {
+            return err!(ErrorCode::DepositsClosed);
+        }
+        if self.stake_start_ts.is_some_and(|start| now < start) {
+            return err!(ErrorCode::StakingNotStarted);
+        }
+        if self.stake_end_ts.is_some_and(|end| now >= end) {
+            return err!(ErrorCode::StakingEnded);
+        }
+        Ok(())
+    }
+
+    /// Returns whether the lock-up of `stake_info` is over at `now`. In `Sunset` every lock is.
+    pub fn is_matured(&self, stake_info: &UserStakeInfo, now: i64) -> bool {
+        self.status == ProjectStatus::Sunset || stake_info.unlock_timestamp(now) <= now
+    }
+
+    /// Returns the unbonding cooldown of `stake_info`: the one it was created with, or the
+    /// project's current one if that is shorter.
+    pub fn cooldown_for(&self, stake_info: &UserStakeInfo) -> i64 {
+        self.cooldown_seconds.min(stake_info.cooldown_seconds)
+    }
+
+    /// Returns whether `stake_info` must leave through `request_unstake` and `withdraw`.
+    /// A `Sunset` project lets it leave directly.
+    pub fn cooldown_required(&self, stake_info: &UserStakeInfo) -> bool {
+        self.cooldown_for(stake_info) > 0 && self.status != ProjectStatus::Sunset
+    }
+
+    /// Adds `amount` to `total_staked`, failing if it would exceed `max_total_staked`.
+    pub fn add_staked(&mut self, amount: u64) -> Result<()> {
+        let total_staked = self.total_staked.checked_add(amount).ok_or(ErrorCode::MathOverflow)?;
+        if self.max_total_staked > 0 && total_staked > self.max_total_staked {
+            return err!(ErrorCode::TotalStakeCapExceeded);
+        }
+        self.total_staked = total_staked;
+        Ok(())
+    }
+
+    /// Removes `amount` from `total_staked` once it leaves the vault.
+    pub fn remove_staked(&mut self, amount: u64) -> Result<()> {
+        self.total_staked = self.total_staked.checked_sub(amount).ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Increments `active_stake_count` when a stake is opened.
+    pub fn add_active_stake(&mut self) -> Result<()> {
+        self.active_stake_count = self.active_stake_count.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Decrements `active_stake_count` when a stake stops being active.
+    pub fn remove_active_stake(&mut self) -> Result<()> {
+        self.active_stake_count = self.active_stake_count.checked_sub(1).ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Returns the normal unstake fee, in basis points, charged to `stake_info`.
+    pub fn unstake_fee_for(&self, stake_info: &UserStakeInfo) -> u16 {
+        if self.status == ProjectStatus::Sunset && self.sunset_fee_waived {
+            return 0;
+        }
+        match stake_info.fee_mode {
+            FeeMode::Snapshot => stake_info.unstake_fee_bps,
+            FeeMode::Current => self.unstake_fee_bps,
+        }
+    }
+
+    /// Returns the emergency unstake fee, in basis points, charged to `stake_info` before it matures.
+    pub fn emergency_unstake_fee_for(&self, stake_info: &UserStakeInfo) -> u16 {
+        match stake_info.fee_mode {
+            FeeMode::Snapshot => stake_info.emergency_unstake_fee_bps,
+            FeeMode::Current => self.emergency_unstake_fee_bps,
+        }
+    }
+
+    /// Sets the share of emergency unstake fees redistributed to the remaining stakers.
+    pub fn set_fee_redistribution(&mut self, emergency_fee_redistribution_bps: u16) -> Result<()> {
+        if emergency_fee_redistribution_bps > 10000 {
+            return err!(ErrorCode::InvalidFeeBps);
+        }
+        self.emergency_fee_redistribution_bps = emergency_fee_redistribution_bps;
+        Ok(())
+    }
+
+    /// Returns the account size for a project with the given name length and number of durations.
+    pub fn space(name_len: usize, durations_len: usize) -> usize {
+        8 + 8 + 32 + 32 + 32 + (4 + name_len) + 32 + 32 + 2 + 2 + 2 + (4 + durations_len * (4 + 2))
+            + 1 + MAX_REWARD_STREAMS * RewardStream::LEN + 8 + 1 + 8 + 1 + 1 + (1 + PendingConfig::LEN) + 1 + (1 + 32) + 8 + 8 + 8 + 8 + 8 + 8 + 8 + (1 + 8) + (1 + 8) + 1 + 1
+    }
+
+    /// Returns the reward streams in use.
+    pub fn active_reward_streams(&self) -> &[RewardStream] {
+        &self.reward_streams[..self.reward_stream_count as usize]
+    }
+
+    /// Returns the index of the reward stream paying the staked token, which receives
+    /// redistributed emergency fees.
+    pub fn penalty_reward_stream(&self) -> Option<usize> {
+        self.active_reward_streams()
+            .iter()
+            .position(|stream| stream.mint == self.token_mint)
+    }
+
+    /// Accrues the rewards of every stream up to `now`.
+    ///
+    /// Must be called before any change to `total_weight` or a stream's rate.
+    /// Rewards emitted while nothing is staked are not distributed.
+    pub fn update_rewards(&mut self, now: i64) -> Result<()> {
+        let total_weight = self.total_weight;
+        let count = self.reward_stream_count as usize;
+        for stream in self.reward_streams[..count].iter_mut() {
+            stream.update(now, total_weight)?;
+        }
+        Ok(())
+    }
+}
+
+/// A pending or executed sensitive administrative action and the authorities that approved it.
+#[account]
+pub struct Proposal {
+    /// A unique numerical ID for the proposal.
+    pub proposal_id: u64,
+    /// The authority that created the proposal.
+    pub proposer: Pubkey,
+    /// The action to execute once enough approvals are collected.
+    pub action: ProposalAction,
+    /// The authorities that approved the proposal, the proposer included. Max `MAX_PROPOSAL_APPROVALS`.
+    pub approvals: Vec<Pubkey>,
+    /// The Unix timestamp when the proposal was created.
+    pub created_at: i64,
+    /// Whether the proposal has been executed.
+    pub executed: bool,
+}
+
+impl Proposal {
+    pub const LEN: usize = 8 + 8 + 32 + ProposalAction::LEN + (4 + MAX_PROPOSAL_APPROVALS * 32) + 8 + 1;
+}
+
+/// Holds the details of a single user's stake.
+/// A user can have multiple stake accounts for the same project.
+#[account]
+pub struct UserStakeInfo {
+    /// The user who owns this stake.
+    pub user: Pubkey,
+    /// A reference to the `ProjectConfig` this stake belongs to.
+    pub project_config: Pubkey,
+    /// The ID of the project this stake belongs to.
+    pub project_id: u64,
+    /// A unique identifier for this stake, provided by the client.
+    pub stake_id: u64,
+    /// The amount of tokens staked.
+    pub amount: u64,
+    /// The Unix timestamp from which the lock-up is counted: when the stake was created,
+    /// or as restarted by `increase_stake` or `extend_lock`.
+    pub stake_timestamp: i64,
+    /// The duration of the stake lock-up in days.
+    pub duration_days: u32,
+    /// A flag indicating if the tokens are currently staked.
+    pub is_staked: bool,
+    /// Per reward stream, the stream's `acc_reward_per_share` when this stake's rewards were last settled.
+    pub reward_per_share_paid: [u128; MAX_REWARD_STREAMS],
+    /// The reward weight of this stake: `amount` scaled by its duration's multiplier.
+    /// Zero once the stake has left the reward pool.
+    pub weight: u64,
+    /// Per reward stream, the cumulative amount of rewards paid out to this stake.
+    pub rewards_claimed: [u64; MAX_REWARD_STREAMS],
+    /// Whether the lock-up restarts for the same duration each time it ends.
+    pub auto_renew: bool,
+    /// The project's normal unstake fee when the stake was created. Top-ups keep it.
+    pub unstake_fee_bps: u16,
+    /// The project's emergency unstake fee when the stake was created. Top-ups keep it.
+    pub emergency_unstake_fee_bps: u16,
+    /// Per reward stream, rewards settled but not paid yet: earned before the stake's weight
+    /// last changed, or left out of a claim.
+    pub rewards_owed: [u64; MAX_REWARD_STREAMS],
+    /// The project's fee mode when the stake was created, so later mode changes cannot
+    /// reach stakes made under the timelocked fees.
+    pub fee_mode: FeeMode,
+    /// The project's unbonding cooldown when the stake was created, so later increases
+    /// cannot hold back its exit.
+    pub cooldown_seconds: i64,
+}
+
+impl UserStakeInfo {
+    /// Returns the lock-up duration in seconds.
+    pub fn duration_seconds(&self) -> i64 {
+        (self.duration_days as i64) * 24 * 60 * 60
+    }
+
+    /// Returns the Unix timestamp at which the stake's lock-up ends, as seen at `now`.
+    ///
+    /// An auto-renewing stake is renewed lazily: once a cycle ends, the next one starts
+    /// right away, so its unlock time is always the end of the cycle containing `now`.
+    pub fn unlock_timestamp(&self, now: i64) -> i64 {
+        let duration = self.duration_seconds();
+        let unlock_timestamp = self.stake_timestamp + duration;
+        if !self.auto_renew || duration == 0 || unlock_timestamp > now {
+            return unlock_timestamp;
+        }
+        let elapsed_cycles = (now - self.stake_timestamp) / duration;
+        self.stake_timestamp + (elapsed_cycles + 1) * duration
+    }
+
+    /// Returns the rewards earned from stream `index` and not paid yet, in reward token units.
+    ///
+    /// Only the accumulator's growth since the last settlement is multiplied by the weight.
+    /// The stake was in the pool for all of that growth, so the product stays below
+    /// `u64::MAX * REWARD_PRECISION` however large the accumulator itself becomes.
+    pub fn pending_rewards(&self, index: usize, acc_reward_per_share: u128) -> Result<u64> {
+        let growth = acc_reward_per_share
+            .checked_sub(self.reward_per_share_paid[index])
+            .ok_or(ErrorCode::MathOverflow)?;
+        let earned = (self.weight as u128)
+            .checked_mul(growth)
+            .ok_or(ErrorCode::MathOverflow)?
+            / REWARD_PRECISION;
+        let earned = u64::try_from(earned).map_err(|_| error!(ErrorCode::MathOverflow))?;
+        Ok(earned
+            .checked_add(self.rewards_owed[index])
+            .ok_or(ErrorCode::MathOverflow)?)
+    }
+
+    /// Starts accruing from the streams' current accumulators. Rewards already owed are kept.
+    pub fn checkpoint_rewards(&mut self, streams: &[RewardStream]) {
+        for (index, stream) in streams.iter().enumerate() {
+            self.reward_per_share_paid[index] = stream.acc_reward_per_share;
+        }
+    }
+
+    /// Changes the stake's weight while keeping the rewards it has not been paid yet.
+    pub fn reweight(&mut self, new_weight: u64, streams: &[RewardStream]) -> Result<()> {
+        for (index, stream) in streams.iter().enumerate() {
+            self.rewards_owed[index] = self.pending_rewards(index, stream.acc_reward_per_share)?;
+        }
+        self.checkpoint_rewards(streams);
+        self.weight = new_weight;
+        Ok(())
+    }
+
+    /// Takes a settled stake out of the reward pool, keeping `unpaid` owed so it can still be claimed.
+    pub fn leave_pool(&mut self, unpaid: [u64; MAX_REWARD_STREAMS]) {
+        self.rewards_owed = unpaid;
+        self.weight = 0;
+    }
+
+    /// Takes the pending rewards of every stream out of the stake and returns their amounts.
+    pub fn settle_rewards(&mut self, streams: &[RewardStream]) -> Result<[u64; MAX_REWARD_STREAMS]> {
+        let mut payouts = [0; MAX_REWARD_STREAMS];
+        for (index, stream) in streams.iter().enumerate() {
+            payouts[index] = self.pending_rewards(index, stream.acc_reward_per_share)?;
+        }
+        self.rewards_owed = [0; MAX_REWARD_STREAMS];
+        self.checkpoint_rewards(streams);
+        Ok(payouts)
+    }
+}
+
+/// Tracks how much a user has staked in a project across all their stakes.
+///
+/// Created on the user's first stake in the project, derived from the seeds
+/// "user-project", the project config and the user.
+#[account]
+pub struct UserProjectInfo {
+    /// The user.
+    pub user: Pubkey,
+    /// A reference to the `ProjectConfig`.
+    pub project_config: Pubkey,
+    /// The total amount the user has in active stakes of the project.
+    pub total_staked: u64,
+}
+
+impl UserProjectInfo {
+    pub const LEN: usize = 32 + 32 + 8;
+
+    /// Adds `amount` to the user's total, failing if it would exceed `max_per_user` (zero means no cap).
+    pub fn add_stake(&mut self, amount: u64, max_per_user: u64) -> Result<()> {
+        let total_staked = self.total_staked.checked_add(amount).ok_or(ErrorCode::MathOverflow)?;
+        if max_per_user > 0 && total_staked > max_per_user {
+            return err!(ErrorCode::UserStakeCapExceeded);
+        }
+        self.total_staked = total_staked;
+        Ok(())
+    }
+
+    /// Removes `amount` from the user's total once it leaves the project.
+    pub fn remove_stake(&mut self, amount: u64) -> Result<()> {
+        self.total_staked = self.total_staked.checked_sub(amount).ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+}
+
+/// Holds the details of a single user's unstake action.
+#[account]
+pub struct UnstakeInfo {
+    /// The user who unstaked.
+    pub user: Pubkey,
+    /// A reference to the `ProjectConfig` this unstake belongs to.
+    pub project_config: Pubkey,
+    /// The ID of the project this unstake belongs to.
+    pub project_id: u64,
+    /// A reference back to the original `UserStakeInfo` account.
+    pub stake_info: Pubkey,
+    /// The unique identifier for the original stake.
+    pub stake_id: u64,
+    /// The amount of tokens unstaked.
+    pub amount: u64,
+    /// The Unix timestamp when the unstake occurred, or was requested while `Cooling`.
+    pub unstake_timestamp: i64,
+    /// The status of the unstake (Unstaked, EmergencyUnstaked or Cooling).
+    pub status: StakeStatus,
+    /// The Unix timestamp from which a `Cooling` unstake can be withdrawn.
+    pub withdrawable_timestamp: i64,
+}
+
+/// Checks that a role bitmask is non-empty and only holds known `ROLE_*` bits.
+fn validate_roles(roles: u8) -> Result<()> {
+    if roles == 0 || roles & !ALL_ROLES != 0 {
+        return err!(ErrorCode::InvalidRoles);
+    }
+    Ok(())
+}
+
+/// Checks that a list of allowed durations is short enough, has no duplicate
+/// durations and only carries multipliers between 1x and `MAX_MULTIPLIER_BPS`.
+fn validate_allowed_durations(durations: &[AllowedDuration]) -> Result<()> {
+    if durations.len() > 10 {
+        return err!(ErrorCode::TooManyDurations);
+    }
+    let mut de_dup_check: Vec<u32> = durations.iter().map(|d| d.duration_days).collect();
+    de_dup_check.sort_unstable();
+    de_dup_check.dedup();
+    if de_dup_check.len() != durations.len() {
+        return err!(ErrorCode::DuplicateDurations);
+    }
+    if durations
+        .iter()
+        .any(|d| (d.multiplier_bps as u64) < BPS_DENOMINATOR || d.multiplier_bps > MAX_MULTIPLIER_BPS)
+    {
+        return err!(ErrorCode::InvalidMultiplier);
+    }
+    Ok(())
+}
+
+/// Fails with `StakingPaused` if new stakes are blocked on the platform or the project.
+fn check_staking_allowed(platform_config: &PlatformConfig, project_config: &ProjectConfig) -> Result<()> {
+    if platform_config.paused || project_config.paused {
+        return err!(ErrorCode::StakingPaused);
+    }
+    Ok(())
+}
+
+/// Fails with `UnstakingPaused` if unstaking is blocked on the platform or the project.
+fn check_unstaking_allowed(platform_config: &PlatformConfig, project_config: &ProjectConfig) -> Result<()> {
+    if platform_config.unstake_paused || project_config.unstake_paused {
+        return err!(ErrorCode::UnstakingPaused);
+    }
+    Ok(())
+}
+
+/// Returns the fee, in tokens, charged on `amount` at `fee_bps` basis points.
+fn calculate_fee(amount: u64, fee_bps: u16) -> Result<u64> {
+    let fee = (amount as u128)
+        .checked_mul(fee_bps as u128)
+        .ok_or(ErrorCode::MathOverflow)?
+        / BPS_DENOMINATOR as u128;
+    u64::try_from(fee).map_err(|_| error!(ErrorCode::MathOverflow))
+}
+
+/// Returns the reward weight of `amount` staked with the given multiplier.
+fn stake_weight(amount: u64, multiplier_bps: u16) -> Result<u64> {
+    let weight = (amount as u128)
+        .checked_mul(multiplier_bps as u128)
+        .ok_or(ErrorCode::MathOverflow)?
+        / BPS_DENOMINATOR as u128;
+    u64::try_from(weight).map_err(|_| error!(ErrorCode::MathOverflow))
+}
+
+/// Transfers `amount` tokens of `mint` out of a program-owned vault, signed by the `vault-authority` PDA.
+fn transfer_from_vault<'info>(
+    token_program: AccountInfo<'info>,
+    from: AccountInfo<'info>,
+    mint: AccountInfo<'info>,
+    to: AccountInfo<'info>,
+    authority: AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+    amount: u64,
+) -> Result<()> {
+    let decimals = Mint::try_deserialize(&mut &mint.try_borrow_data()?[..])?.decimals;
+    let cpi_accounts = TransferChecked { from, mint, to, authority };
+    let cpi_ctx = CpiContext::new_with_signer(token_program, cpi_accounts, signer_seeds);
+    token_interface::transfer_checked(cpi_ctx, amount, decimals)
+}
+
+/// Pays out settled rewards for the reward streams whose accounts are passed.
+///
+/// `remaining_accounts` holds `REWARD_ACCOUNTS_PER_STREAM` accounts for each stream to pay:
+/// the stream's reward vault, the user's token account for the reward mint, the reward mint
+/// and the stream's token program. Streams without accounts are skipped, so a frozen or paused reward mint
+/// cannot block the others. Returns the amounts left unpaid, per stream.
+fn pay_rewards<'info>(
+    project_config: &mut ProjectConfig,
+    stake_info: &mut UserStakeInfo,
+    payouts: &[u64; MAX_REWARD_STREAMS],
+    remaining_accounts: &[AccountInfo<'info>],
+    vault_authority: &AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<[u64; MAX_REWARD_STREAMS]> {
+    let chunks = remaining_accounts.chunks_exact(REWARD_ACCOUNTS_PER_STREAM);
+    if !chunks.remainder().is_empty() {
+        return err!(ErrorCode::MissingRewardAccounts);
+    }
+    let count = project_config.reward_stream_count as usize;
+    let mut unpaid = *payouts;
+    for accounts in chunks {
+        let [reward_vault, user_reward_token_account, reward_mint, token_program] = accounts else {
+            return err!(ErrorCode::MissingRewardAccounts);
+        };
+        let index = project_config.reward_streams[..count]
+            .iter()
+            .position(|stream| stream.vault == reward_vault.key())
+            .ok_or(ErrorCode::InvalidRewardAccount)?;
+        let stream = &mut project_config.reward_streams[index];
+        if reward_mint.key() != stream.mint || token_program.key() != stream.token_program {
+            return err!(ErrorCode::InvalidRewardAccount);
+        }
+        if user_reward_token_account.owner != &stream.token_program {
+            return err!(ErrorCode::InvalidRewardAccount);
+        }
+        let user_reward_token = TokenAccount::try_deserialize(&mut &user_reward_token_account.try_borrow_data()?[..])?;
+        if user_reward_token.mint != stream.mint {
+            return err!(ErrorCode::InvalidRewardAccount);
+        }
+
+        let amount = unpaid[index];
+        if amount == 0 {
+            continue;
+        }
+        transfer_from_vault(
+            token_program.clone(),
+            reward_vault.clone(),
+            reward_mint.clone(),
+            user_reward_token_account.clone(),
+            vault_authority.clone(),
+            signer_seeds,
+            amount,
+        )?;
+        unpaid[index] = 0;
+        stream.total_claimed = stream
+            .total_claimed
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        stake_info.rewards_claimed[index] = stake_info.rewards_claimed[index]
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        emit!(RewardClaimedEvent {
+            user: stake_info.user,
+            project_id: stake_info.project_id,
+            stake_id: stake_info.stake_id,
+            reward_mint: stream.mint,
+            amount,
+            total_claimed: stake_info.rewards_claimed[index],
+        });
+    }
+    Ok(unpaid)
+}
+
+/// Gives up rewards settled for a stake that emergency unstakes.
+///
+/// They no longer count as owed to stakers, so the tokens become surplus of the reward vault.
+fn forfeit_rewards(project_config: &mut ProjectConfig, stake_info: &UserStakeInfo, amounts: &[u64; MAX_REWARD_STREAMS]) {
+    let count = project_config.reward_stream_count as usize;
+    for (stream, amount) in project_config.reward_streams[..count].iter_mut().zip(amounts) {
+        if *amount == 0 {
+            continue;
+        }
+        stream.total_distributed = stream.total_distributed.saturating_sub(*amount);
+        emit!(RewardForfeitedEvent {
+            user: stake_info.user,
+            project_id: stake_info.project_id,
+            stake_id: stake_info.stake_id,
+            reward_mint: stream.mint,
+            amount: *amount,
+        });
+    }
+}
+
+// ============== CONTEXTS ==============
+
+#[derive(Accounts)]
+pub struct InitializePlatform<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = PlatformConfig::space(1, 0),
+        seeds = [b"platform"],
+        bump
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterProject<'info> {
+    #[account(
+        mut,
+        seeds = [b"platform"],
+        bump
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(
+        init,
+        payer = authority,
+        // Name up to 32 bytes and up to 10 durations
+        space = ProjectConfig::space(32, 10),
+        seeds = [b"project", platform_config.project_count.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub project_config: Account<'info, ProjectConfig>,
+    pub token_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        init,
+        payer = authority,
+        token::mint = token_mint,
+        token::authority = vault_authority,
+        seeds = [b"vault", platform_config.project_count.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: PDA used as vault authority
+    #[account(
+        seeds = [b"vault-authority", platform_config.project_count.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+#[instruction(new_durations: Vec<AllowedDuration>)]
+pub struct UpdateAllowedDurations<'info> {
+    #[account(
+        seeds = [b"platform"],
+        bump
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(
+        mut,
+        realloc = ProjectConfig::space(project_config.name.as_bytes().len(), new_durations.len()),
+        realloc::payer = authority,
+        realloc::zero = false,
+    )]
+    pub project_config: Account<'info, ProjectConfig>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateProjectConfig<'info> {
+    #[account(
+        seeds = [b"platform"],
+        bump
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(mut)]
+    pub project_config: Account<'info, ProjectConfig>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SweepSurplus<'info> {
+    #[account(
+        seeds = [b"platform"],
+        bump
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(
+        has_one = vault,
+        has_one = token_mint,
+        constraint = project_config.token_program == token_program.key()
+    )]
+    pub project_config: Account<'info, ProjectConfig>,
+    pub token_mint: InterfaceAccount<'info, Mint>,
+    #[account(mut)]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: PDA used as vault authority
+    #[account(
+        seeds = [b"vault-authority", project_config.project_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        constraint = to.mint == project_config.token_mint
+    )]
+    pub to: InterfaceAccount<'info, TokenAccount>,
+    pub authority: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct SweepRewardSurplus<'info> {
+    #[account(
+        seeds = [b"platform"],
+        bump
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(mut)]
+    pub project_config: Account<'info, ProjectConfig>,
+    #[account(mut)]
+    pub reward_vault: InterfaceAccount<'info, TokenAccount>,
+    pub reward_mint: InterfaceAccount<'info, Mint>,
+    /// CHECK: PDA used as reward vault authority
+    #[account(
+        seeds = [b"vault-authority", project_config.project_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        constraint = to.mint == reward_mint.key()
+    )]
+    pub to: InterfaceAccount<'info, TokenAccount>,
+    pub authority: Signer<'info>,
+    pub reward_token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct CloseProject<'info> {
+    #[account(
+        seeds = [b"platform"],
+        bump
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(
+        mut,
+        close = receiver,
+        has_one = vault,
+        constraint = project_config.token_program == token_program.key(),
+        constraint = project_config.total_staked == 0 @ ErrorCode::ProjectNotEmpty
+    )]
+    pub project_config: Account<'info, ProjectConfig>,
+    #[account(
+        mut,
+        constraint = vault.amount == 0 @ ErrorCode::VaultNotEmpty
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: PDA used as vault authority
+    #[account(
+        seeds = [b"vault-authority", project_config.project_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+    /// CHECK: Only receives the rent of the closed accounts.
+    #[account(mut)]
+    pub receiver: UncheckedAccount<'info>,
+    pub authority: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptProjectAuthority<'info> {
+    #[account(
+        mut,
+        constraint = project_config.pending_authority == Some(new_authority.key()) @ ErrorCode::NotPendingProjectAuthority
+    )]
+    pub project_config: Account<'info, ProjectConfig>,
+    pub new_authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ApplyPendingConfig<'info> {
+    #[account(mut)]
+    pub project_config: Account<'info, ProjectConfig>,
+}
+
+#[derive(Accounts)]
+pub struct SetPaused<'info> {
+    #[account(
+        mut,
+        seeds = [b"platform"],
+        bump
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(mut)]
+    pub project_config: Option<Account<'info, ProjectConfig>>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AddRewardStream<'info> {
+    #[account(
+        seeds = [b"platform"],
+        bump
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(mut)]
+    pub project_config: Account<'info, ProjectConfig>,
+    #[account(mint::token_program = reward_token_program)]
+    pub reward_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        init,
+        payer = authority,
+        token::mint = reward_mint,
+        token::authority = vault_authority,
+        token::token_program = reward_token_program,
+        seeds = [b"reward-vault", project_config.project_id.to_le_bytes().as_ref(), &[project_config.reward_stream_count]],
+        bump
+    )]
+    pub reward_vault: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: PDA used as vault authority
+    #[account(
+        seeds = [b"vault-authority", project_config.project_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    pub reward_token_program: Interface<'info, TokenInterface>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct FundRewards<'info> {
+    #[account(
+        seeds = [b"platform"],
+        bump
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(mut)]
+    pub project_config: Account<'info, ProjectConfig>,
+    #[account(mut)]
+    pub reward_vault: InterfaceAccount<'info, TokenAccount>,
+    pub reward_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        constraint = funder_token_account.mint == reward_vault.mint,
+        constraint = funder_token_account.owner == authority.key()
+    )]
+    pub funder_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub reward_token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateRewardRate<'info> {
+    #[account(
+        seeds = [b"platform"],
+        bump
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(mut)]
+    pub project_config: Account<'info, ProjectConfig>,
+    pub reward_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct NominateAuthority<'info> {
+    #[account(
+        mut,
+        seeds = [b"platform"],
+        bump,
+        realloc = PlatformConfig::space(platform_config.authorities.len(), platform_config.nominations.len() + 1),
+        realloc::payer = authority,
+        realloc::zero = false,
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAuthority<'info> {
+    #[account(
+        mut,
+        seeds = [b"platform"],
+        bump,
+        realloc = PlatformConfig::space(platform_config.authorities.len() + 1, platform_config.nominations.len()),
+        realloc::payer = nominee,
+        realloc::zero = false,
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(mut)]
+    pub nominee: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetAuthorityRoles<'info> {
+    #[account(
+        mut,
+        seeds = [b"platform"],
+        bump,
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct Propose<'info> {
+    #[account(
+        mut,
+        seeds = [b"platform"],
+        bump,
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(
+        init,
+        payer = proposer,
+        space = Proposal::LEN,
+        seeds = [b"proposal", platform_config.proposal_count.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub proposal: Account<'info, Proposal>,
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+    pub system_program: Program<'info, System>,
 }
 
-/// Holds the details of a single user's unstake action.
-#[account]
-pub struct UnstakeInfo {
-    /// The user who unstaked.
-    pub user: Pubkey,
-    /// A reference to the `ProjectConfig` this unstake belongs to.
-    pub project_config: Pubkey,
-    /// The ID of the project this unstake belongs to.
-    pub project_id: u64,
-    /// A reference back to the original `UserStakeInfo` account.
-    pub stake_info: Pubkey,
-    /// The unique identifier for the original stake.
-    pub stake_id: u64,
-    /// The amount of tokens unstaked.
-    pub amount: u64,
-    /// The Unix timestamp when the unstake occurred.
-    pub unstake_timestamp: i64,
-    /// The status of the unstake (Unstaked or EmergencyUnstaked).
-    pub status: StakeStatus,
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct Approve<'info> {
+    #[account(
+        seeds = [b"platform"],
+        bump,
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(
+        mut,
+        seeds = [b"proposal", proposal_id.to_le_bytes().as_ref()],
+        bump,
+        constraint = !proposal.executed @ ErrorCode::ProposalAlreadyExecuted
+    )]
+    pub proposal: Account<'info, Proposal>,
+    pub approver: Signer<'info>,
 }
 
-// ============== CONTEXTS ==============
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct Execute<'info> {
+    #[account(
+        mut,
+        seeds = [b"platform"],
+        bump,
+        // Room for one more nomination, in case the proposal adds one
+        realloc = PlatformConfig::space(platform_config.authorities.len(), platform_config.nominations.len() + 1),
+        realloc::payer = executor,
+        realloc::zero = false,
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(
+        mut,
+        seeds = [b"proposal", proposal_id.to_le_bytes().as_ref()],
+        bump,
+        constraint = !proposal.executed @ ErrorCode::ProposalAlreadyExecuted
+    )]
+    pub proposal: Account<'info, Proposal>,
+    #[account(mut)]
+    pub project_config: Option<Account<'info, ProjectConfig>>,
+    #[account(mut)]
+    pub executor: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
 
 #[derive(Accounts)]
-pub struct InitializePlatform<'info> {
+pub struct RemoveAuthority<'info> {
     #[account(
-        init,
-        payer = authority,
-        // 8 discriminator + 4 vec prefix + 1 * 32 for the first authority + 8 for project_count
-        space = 8 + 4 + 32 + 8,
+        mut,
         seeds = [b"platform"],
-        bump
+        bump,
+        realloc = platform_config.to_account_info().data_len() - PlatformAuthority::LEN,
+        realloc::payer = authority,
+        realloc::zero = false,
     )]
     pub platform_config: Account<'info, PlatformConfig>,
     #[account(mut)]
@@ -536,48 +3514,60 @@ pub struct InitializePlatform<'info> {
 }
 
 #[derive(Accounts)]
-pub struct RegisterProject<'info> {
+#[instruction(amount: u64, duration_days: u32, stake_id: u64, auto_renew: bool)]
+pub struct Stake<'info> {
     #[account(
-        mut,
         seeds = [b"platform"],
         bump
     )]
     pub platform_config: Account<'info, PlatformConfig>,
     #[account(
-        init,
-        payer = authority,
-        // Base: 214 + Vec (4 prefix + 10 * 4 items) = 258
-        space = 8 + 8 + 32 + 32 + 32 + (4 + 32) + 32 + 32 + 2 + 2 + (4 + 10 * 4),
-        seeds = [b"project", platform_config.project_count.to_le_bytes().as_ref()],
-        bump
+        mut,
+        has_one = vault,
+        constraint = project_config.token_program == token_program.key()
     )]
     pub project_config: Account<'info, ProjectConfig>,
-    pub token_mint: InterfaceAccount<'info, Mint>,
     #[account(
         init,
-        payer = authority,
-        token::mint = token_mint,
-        token::authority = vault_authority,
-        seeds = [b"vault", platform_config.project_count.to_le_bytes().as_ref()],
+        payer = user,
+        space = 8 + 32 + 32 + 8 + 8 + 8 + 8 + 4 + 1 + 16 * MAX_REWARD_STREAMS + 8 + 8 * MAX_REWARD_STREAMS + 1 + 2 + 2 + 8 * MAX_REWARD_STREAMS + 1 + 8,
+        seeds = [b"stake", project_config.key().to_bytes().as_ref(), user.key().as_ref(), stake_id.to_le_bytes().as_ref()],
         bump
     )]
-    pub vault: InterfaceAccount<'info, TokenAccount>,
-    /// CHECK: PDA used as vault authority
+    pub stake_info: Account<'info, UserStakeInfo>,
     #[account(
-        seeds = [b"vault-authority", platform_config.project_count.to_le_bytes().as_ref()],
+        init_if_needed,
+        payer = user,
+        space = 8 + UserProjectInfo::LEN,
+        seeds = [b"user-project", project_config.key().as_ref(), user.key().as_ref()],
         bump
     )]
-    pub vault_authority: UncheckedAccount<'info>,
+    pub user_project_info: Account<'info, UserProjectInfo>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + 137,
+        seeds = [b"unstake", stake_info.key().as_ref()],
+        bump
+    )]
+    pub unstake_info: Account<'info, UnstakeInfo>,
     #[account(mut)]
-    pub authority: Signer<'info>,
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        constraint = user_token_account.mint == project_config.token_mint,
+        constraint = user_token_account.owner == user.key()
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
     pub system_program: Program<'info, System>,
     pub token_program: Interface<'info, TokenInterface>,
-    pub rent: Sysvar<'info, Rent>,
 }
 
 #[derive(Accounts)]
-#[instruction(new_durations: Vec<u32>)]
-pub struct UpdateAllowedDurations<'info> {
+#[instruction(stake_id: u64)]
+pub struct IncreaseStake<'info> {
     #[account(
         seeds = [b"platform"],
         bump
@@ -585,19 +3575,72 @@ pub struct UpdateAllowedDurations<'info> {
     pub platform_config: Account<'info, PlatformConfig>,
     #[account(
         mut,
-        has_one = authority,
-        realloc = 8 + 8 + 32 + 32 + 32 + (4 + project_config.name.as_bytes().len()) + 32 + 32 + 2 + 2 + (4 + new_durations.len() * 4),
-        realloc::payer = authority,
-        realloc::zero = false,
+        has_one = vault,
+        has_one = token_mint,
+        constraint = project_config.token_program == token_program.key()
     )]
     pub project_config: Account<'info, ProjectConfig>,
+    #[account(
+        mut,
+        has_one = user,
+        seeds = [b"stake", project_config.key().to_bytes().as_ref(), user.key().as_ref(), stake_id.to_le_bytes().as_ref()],
+        bump,
+        constraint = stake_info.is_staked @ ErrorCode::StakeNotActive
+    )]
+    pub stake_info: Account<'info, UserStakeInfo>,
+    #[account(
+        mut,
+        seeds = [b"user-project", project_config.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub user_project_info: Account<'info, UserProjectInfo>,
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        constraint = user_token_account.mint == project_config.token_mint,
+        constraint = user_token_account.owner == user.key()
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub token_mint: InterfaceAccount<'info, Mint>,
     #[account(mut)]
-    pub authority: Signer<'info>,
-    pub system_program: Program<'info, System>,
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
 #[derive(Accounts)]
-pub struct UpdateProjectConfig<'info> {
+#[instruction(stake_id: u64)]
+pub struct ExtendLock<'info> {
+    #[account(mut)]
+    pub project_config: Account<'info, ProjectConfig>,
+    #[account(
+        mut,
+        has_one = user,
+        seeds = [b"stake", project_config.key().to_bytes().as_ref(), user.key().as_ref(), stake_id.to_le_bytes().as_ref()],
+        bump,
+        constraint = stake_info.is_staked @ ErrorCode::StakeNotActive
+    )]
+    pub stake_info: Account<'info, UserStakeInfo>,
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(stake_id: u64)]
+pub struct SetAutoRenew<'info> {
+    pub project_config: Account<'info, ProjectConfig>,
+    #[account(
+        mut,
+        has_one = user,
+        seeds = [b"stake", project_config.key().to_bytes().as_ref(), user.key().as_ref(), stake_id.to_le_bytes().as_ref()],
+        bump,
+        constraint = stake_info.is_staked @ ErrorCode::StakeNotActive
+    )]
+    pub stake_info: Account<'info, UserStakeInfo>,
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(stake_id: u64)]
+pub struct Unstake<'info> {
     #[account(
         seeds = [b"platform"],
         bump
@@ -605,87 +3648,171 @@ pub struct UpdateProjectConfig<'info> {
     pub platform_config: Account<'info, PlatformConfig>,
     #[account(
         mut,
-        has_one = authority,
+        has_one = token_mint,
+        constraint = project_config.token_program == token_program.key()
     )]
     pub project_config: Account<'info, ProjectConfig>,
-    #[account(mut)]
-    pub authority: Signer<'info>,
-}
-
-#[derive(Accounts)]
-pub struct AddAuthority<'info> {
     #[account(
         mut,
-        seeds = [b"platform"],
+        has_one = user,
+        seeds = [b"stake", project_config.key().to_bytes().as_ref(), user.key().as_ref(), stake_id.to_le_bytes().as_ref()],
         bump,
-        realloc = platform_config.to_account_info().data_len() + 32,
-        realloc::payer = authority,
-        realloc::zero = false,
+        constraint = stake_info.is_staked @ ErrorCode::StakeNotActive
     )]
-    pub platform_config: Account<'info, PlatformConfig>,
+    pub stake_info: Account<'info, UserStakeInfo>,
+    #[account(
+        mut,
+        seeds = [b"user-project", project_config.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub user_project_info: Account<'info, UserProjectInfo>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + 137,
+        seeds = [b"unstake", stake_info.key().as_ref()],
+        bump
+    )]
+    pub unstake_info: Account<'info, UnstakeInfo>,
     #[account(mut)]
-    pub authority: Signer<'info>,
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        constraint = user_token_account.mint == project_config.token_mint
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub token_mint: InterfaceAccount<'info, Mint>,
+    #[account(mut,
+        seeds = [b"vault", project_config.project_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: PDA used as vault authority
+    #[account(
+        seeds = [b"vault-authority", project_config.project_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        constraint = fee_wallet.mint == project_config.token_mint,
+        constraint = fee_wallet.owner == project_config.fee_wallet @ ErrorCode::InvalidFeeWallet
+    )]
+    pub fee_wallet: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct RemoveAuthority<'info> {
+#[instruction(stake_id: u64)]
+pub struct RequestUnstake<'info> {
     #[account(
-        mut,
         seeds = [b"platform"],
-        bump,
-        realloc = platform_config.to_account_info().data_len() - 32,
-        realloc::payer = authority,
-        realloc::zero = false,
+        bump
     )]
     pub platform_config: Account<'info, PlatformConfig>,
     #[account(mut)]
-    pub authority: Signer<'info>,
+    pub project_config: Account<'info, ProjectConfig>,
+    #[account(
+        mut,
+        has_one = user,
+        seeds = [b"stake", project_config.key().to_bytes().as_ref(), user.key().as_ref(), stake_id.to_le_bytes().as_ref()],
+        bump,
+        constraint = stake_info.is_staked @ ErrorCode::StakeNotActive
+    )]
+    pub stake_info: Account<'info, UserStakeInfo>,
+    #[account(
+        mut,
+        seeds = [b"user-project", project_config.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub user_project_info: Account<'info, UserProjectInfo>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + 137,
+        seeds = [b"unstake", stake_info.key().as_ref()],
+        bump
+    )]
+    pub unstake_info: Account<'info, UnstakeInfo>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    /// CHECK: PDA used as vault authority
+    #[account(
+        seeds = [b"vault-authority", project_config.project_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-#[instruction(amount: u64, duration_days: u32, stake_id: u64)]
-pub struct Stake<'info> {
+#[instruction(stake_id: u64)]
+pub struct Withdraw<'info> {
     #[account(
-        has_one = vault,
+        seeds = [b"platform"],
+        bump
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(
+        mut,
+        has_one = token_mint,
         constraint = project_config.token_program == token_program.key()
     )]
     pub project_config: Account<'info, ProjectConfig>,
     #[account(
-        init,
-        payer = user,
-        space = 8 + 32 + 32 + 8 + 8 + 8 + 8 + 4 + 1,
+        has_one = user,
         seeds = [b"stake", project_config.key().to_bytes().as_ref(), user.key().as_ref(), stake_id.to_le_bytes().as_ref()],
         bump
     )]
     pub stake_info: Account<'info, UserStakeInfo>,
     #[account(
-        init_if_needed,
-        payer = user,
-        space = 8 + 129,
+        mut,
         seeds = [b"unstake", stake_info.key().as_ref()],
-        bump
+        bump,
+        constraint = unstake_info.status == StakeStatus::Cooling @ ErrorCode::NotCooling
     )]
     pub unstake_info: Account<'info, UnstakeInfo>,
-    #[account(mut)]
     pub user: Signer<'info>,
     #[account(
         mut,
-        constraint = user_token_account.mint == project_config.token_mint,
-        constraint = user_token_account.owner == user.key()
+        constraint = user_token_account.mint == project_config.token_mint
     )]
     pub user_token_account: InterfaceAccount<'info, TokenAccount>,
-    #[account(mut)]
+    pub token_mint: InterfaceAccount<'info, Mint>,
+    #[account(mut,
+        seeds = [b"vault", project_config.project_id.to_le_bytes().as_ref()],
+        bump
+    )]
     pub vault: InterfaceAccount<'info, TokenAccount>,
-    pub system_program: Program<'info, System>,
+    /// CHECK: PDA used as vault authority
+    #[account(
+        seeds = [b"vault-authority", project_config.project_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        constraint = fee_wallet.mint == project_config.token_mint,
+        constraint = fee_wallet.owner == project_config.fee_wallet @ ErrorCode::InvalidFeeWallet
+    )]
+    pub fee_wallet: InterfaceAccount<'info, TokenAccount>,
     pub token_program: Interface<'info, TokenInterface>,
 }
 
 #[derive(Accounts)]
 #[instruction(stake_id: u64)]
-pub struct Unstake<'info> {
-    #[account(constraint = project_config.token_program == token_program.key())]
+pub struct UnstakePartial<'info> {
+    #[account(
+        seeds = [b"platform"],
+        bump
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(
+        mut,
+        has_one = token_mint,
+        constraint = project_config.token_program == token_program.key()
+    )]
     pub project_config: Account<'info, ProjectConfig>,
     #[account(
         mut,
@@ -696,20 +3823,18 @@ pub struct Unstake<'info> {
     )]
     pub stake_info: Account<'info, UserStakeInfo>,
     #[account(
-        init_if_needed,
-        payer = user,
-        space = 8 + 129,
-        seeds = [b"unstake", stake_info.key().as_ref()],
+        mut,
+        seeds = [b"user-project", project_config.key().as_ref(), user.key().as_ref()],
         bump
     )]
-    pub unstake_info: Account<'info, UnstakeInfo>,
-    #[account(mut)]
+    pub user_project_info: Account<'info, UserProjectInfo>,
     pub user: Signer<'info>,
     #[account(
         mut,
         constraint = user_token_account.mint == project_config.token_mint
     )]
     pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub token_mint: InterfaceAccount<'info, Mint>,
     #[account(mut,
         seeds = [b"vault", project_config.project_id.to_le_bytes().as_ref()],
         bump
@@ -728,13 +3853,21 @@ pub struct Unstake<'info> {
     )]
     pub fee_wallet: InterfaceAccount<'info, TokenAccount>,
     pub token_program: Interface<'info, TokenInterface>,
-    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
 #[instruction(stake_id: u64)]
 pub struct EmergencyUnstake<'info> {
-    #[account(constraint = project_config.token_program == token_program.key())]
+    #[account(
+        seeds = [b"platform"],
+        bump
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(
+        mut,
+        has_one = token_mint,
+        constraint = project_config.token_program == token_program.key()
+    )]
     pub project_config: Account<'info, ProjectConfig>,
     #[account(
         mut,
@@ -744,10 +3877,16 @@ pub struct EmergencyUnstake<'info> {
         constraint = stake_info.is_staked @ ErrorCode::StakeNotActive
     )]
     pub stake_info: Account<'info, UserStakeInfo>,
+    #[account(
+        mut,
+        seeds = [b"user-project", project_config.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub user_project_info: Account<'info, UserProjectInfo>,
     #[account(
         init_if_needed,
         payer = user,
-        space = 8 + 129,
+        space = 8 + 137,
         seeds = [b"unstake", stake_info.key().as_ref()],
         bump
     )]
@@ -759,6 +3898,7 @@ pub struct EmergencyUnstake<'info> {
         constraint = user_token_account.mint == project_config.token_mint
     )]
     pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub token_mint: InterfaceAccount<'info, Mint>,
     #[account(mut,
         seeds = [b"vault", project_config.project_id.to_le_bytes().as_ref()],
         bump
@@ -776,10 +3916,76 @@ pub struct EmergencyUnstake<'info> {
         constraint = fee_wallet.owner == project_config.fee_wallet @ ErrorCode::InvalidFeeWallet
     )]
     pub fee_wallet: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub penalty_reward_vault: Option<InterfaceAccount<'info, TokenAccount>>,
     pub token_program: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+#[instruction(stake_id: u64)]
+pub struct ClosePosition<'info> {
+    /// CHECK: Only used to derive the stake PDA, so positions can still be closed after `close_project`.
+    pub project_config: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        close = user,
+        has_one = user,
+        seeds = [b"stake", project_config.key().to_bytes().as_ref(), user.key().as_ref(), stake_id.to_le_bytes().as_ref()],
+        bump,
+        constraint = !stake_info.is_staked @ ErrorCode::StakeStillActive
+    )]
+    pub stake_info: Account<'info, UserStakeInfo>,
+    #[account(
+        mut,
+        close = user,
+        seeds = [b"unstake", stake_info.key().as_ref()],
+        bump,
+        constraint = unstake_info.status != StakeStatus::Cooling @ ErrorCode::UnstakeStillCooling
+    )]
+    pub unstake_info: Account<'info, UnstakeInfo>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseUserProject<'info> {
+    /// CHECK: Only used to derive the user's PDA, so it can still be closed after `close_project`.
+    pub project_config: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        close = user,
+        has_one = user,
+        seeds = [b"user-project", project_config.key().as_ref(), user.key().as_ref()],
+        bump,
+        constraint = user_project_info.total_staked == 0 @ ErrorCode::UserProjectNotEmpty
+    )]
+    pub user_project_info: Account<'info, UserProjectInfo>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(stake_id: u64)]
+pub struct ClaimRewards<'info> {
+    #[account(mut)]
+    pub project_config: Account<'info, ProjectConfig>,
+    #[account(
+        mut,
+        has_one = user,
+        seeds = [b"stake", project_config.key().to_bytes().as_ref(), user.key().as_ref(), stake_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub stake_info: Account<'info, UserStakeInfo>,
+    pub user: Signer<'info>,
+    /// CHECK: PDA used as vault authority
+    #[account(
+        seeds = [b"vault-authority", project_config.project_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+}
+
 // ============== EVENTS ==============
 
 /// Emitted when a user stakes tokens.
@@ -797,6 +4003,55 @@ pub struct StakeEvent {
     pub duration_days: u32,
 }
 
+/// Emitted when a user adds tokens to an existing stake.
+#[event]
+pub struct StakeIncreasedEvent {
+    /// The user who added the tokens.
+    pub user: Pubkey,
+    /// The ID of the project where the stake is held.
+    pub project_id: u64,
+    /// The unique ID of the stake that was topped up.
+    pub stake_id: u64,
+    /// The amount of tokens added.
+    pub amount: u64,
+    /// The amount of tokens staked after the top-up.
+    pub total_amount: u64,
+    /// The Unix timestamp at which the stake unlocks after the top-up.
+    pub unlock_timestamp: i64,
+}
+
+/// Emitted when a user moves a stake to a longer lock-up.
+#[event]
+pub struct LockExtendedEvent {
+    /// The user who extended the lock-up.
+    pub user: Pubkey,
+    /// The ID of the project where the stake is held.
+    pub project_id: u64,
+    /// The unique ID of the stake that was extended.
+    pub stake_id: u64,
+    /// The lock-up duration in days before the extension.
+    pub old_duration_days: u32,
+    /// The lock-up duration in days after the extension.
+    pub new_duration_days: u32,
+    /// The Unix timestamp at which the stake now unlocks.
+    pub unlock_timestamp: i64,
+}
+
+/// Emitted when a user turns auto-renewal of a stake on or off.
+#[event]
+pub struct AutoRenewUpdatedEvent {
+    /// The user who owns the stake.
+    pub user: Pubkey,
+    /// The ID of the project where the stake is held.
+    pub project_id: u64,
+    /// The unique ID of the stake that was updated.
+    pub stake_id: u64,
+    /// Whether the stake now renews automatically.
+    pub auto_renew: bool,
+    /// The Unix timestamp at which the current lock-up cycle ends.
+    pub unlock_timestamp: i64,
+}
+
 /// Emitted when a user unstakes their tokens after the lock-up period.
 #[event]
 pub struct UnstakeEvent {
@@ -810,6 +4065,36 @@ pub struct UnstakeEvent {
     pub amount: u64,
 }
 
+/// Emitted when a user requests an unstake and the stake enters its unbonding cooldown.
+#[event]
+pub struct UnstakeRequestedEvent {
+    /// The user who requested the unstake.
+    pub user: Pubkey,
+    /// The ID of the project where the stake is held.
+    pub project_id: u64,
+    /// The unique ID of the stake being unstaked.
+    pub stake_id: u64,
+    /// The amount of tokens to be withdrawn, before fees.
+    pub amount: u64,
+    /// The Unix timestamp from which the tokens can be withdrawn.
+    pub withdrawable_timestamp: i64,
+}
+
+/// Emitted when a user withdraws part of a matured stake.
+#[event]
+pub struct PartialUnstakeEvent {
+    /// The user who withdrew the tokens.
+    pub user: Pubkey,
+    /// The ID of the project from which the withdrawal occurred.
+    pub project_id: u64,
+    /// The unique ID of the stake that was reduced.
+    pub stake_id: u64,
+    /// The amount of tokens withdrawn, before fees.
+    pub amount: u64,
+    /// The amount of tokens that remain staked.
+    pub remaining_amount: u64,
+}
+
 /// Emitted when a user performs an emergency unstake.
 #[event]
 pub struct EmergencyUnstakeEvent {
@@ -823,6 +4108,256 @@ pub struct EmergencyUnstakeEvent {
     pub amount: u64,
 }
 
+/// Emitted when the accounts of a finished stake are closed, with its final state.
+#[event]
+pub struct PositionClosedEvent {
+    /// The user who owned the stake.
+    pub user: Pubkey,
+    /// The ID of the project where the stake was held.
+    pub project_id: u64,
+    /// The unique ID of the closed stake.
+    pub stake_id: u64,
+    /// The amount of tokens the stake held when it was unstaked.
+    pub amount: u64,
+    /// The lock-up duration in days.
+    pub duration_days: u32,
+    /// The Unix timestamp from which the last lock-up was counted.
+    pub stake_timestamp: i64,
+    /// The Unix timestamp when the stake was unstaked.
+    pub unstake_timestamp: i64,
+    /// How the stake was unstaked.
+    pub status: StakeStatus,
+    /// Per reward stream, the cumulative amount of rewards paid out to the stake.
+    pub rewards_claimed: [u64; MAX_REWARD_STREAMS],
+}
+
+/// Emitted when rewards are paid out to a stake, either by a claim or on unstake.
+#[event]
+pub struct RewardClaimedEvent {
+    /// The user who received the rewards.
+    pub user: Pubkey,
+    /// The ID of the project that paid the rewards.
+    pub project_id: u64,
+    /// The unique ID of the stake that earned the rewards.
+    pub stake_id: u64,
+    /// The mint of the reward token that was paid out.
+    pub reward_mint: Pubkey,
+    /// The amount of reward tokens paid out.
+    pub amount: u64,
+    /// The cumulative amount of this reward token paid to this stake so far.
+    pub total_claimed: u64,
+}
+
+/// Emitted when a stake leaves the pool without being paid the rewards of a stream.
+#[event]
+pub struct RewardForfeitedEvent {
+    /// The user who gave up the rewards.
+    pub user: Pubkey,
+    /// The ID of the project whose reward vault keeps the tokens.
+    pub project_id: u64,
+    /// The unique ID of the stake that earned the rewards.
+    pub stake_id: u64,
+    /// The mint of the forfeited reward token.
+    pub reward_mint: Pubkey,
+    /// The amount of reward tokens forfeited.
+    pub amount: u64,
+}
+
+/// Emitted when a fee and fee wallet change is queued behind the timelock.
+#[event]
+pub struct ConfigChangeQueuedEvent {
+    /// The ID of the project to change.
+    pub project_id: u64,
+    /// The new wallet to receive fees.
+    pub fee_wallet: Pubkey,
+    /// The new fee in basis points for a normal unstake.
+    pub unstake_fee_bps: u16,
+    /// The new fee in basis points for an emergency unstake.
+    pub emergency_unstake_fee_bps: u16,
+    /// The Unix timestamp from which the change can be applied.
+    pub effective_at: i64,
+}
+
+/// Emitted when a project's fees and fee wallet change.
+#[event]
+pub struct ConfigChangeAppliedEvent {
+    /// The ID of the changed project.
+    pub project_id: u64,
+    /// The wallet now receiving fees.
+    pub fee_wallet: Pubkey,
+    /// The fee in basis points for a normal unstake.
+    pub unstake_fee_bps: u16,
+    /// The fee in basis points for an emergency unstake.
+    pub emergency_unstake_fee_bps: u16,
+}
+
+/// Emitted when surplus tokens are swept from a project's vault.
+#[event]
+pub struct SurplusSweptEvent {
+    /// The ID of the project.
+    pub project_id: u64,
+    /// The token account that received the surplus.
+    pub to: Pubkey,
+    /// The amount swept.
+    pub amount: u64,
+}
+
+/// Emitted when surplus tokens are swept from a reward stream's vault.
+#[event]
+pub struct RewardSurplusSweptEvent {
+    /// The ID of the project.
+    pub project_id: u64,
+    /// The mint of the swept reward stream.
+    pub reward_mint: Pubkey,
+    /// The token account that received the surplus.
+    pub to: Pubkey,
+    /// The amount swept.
+    pub amount: u64,
+}
+
+/// Emitted when an empty project and its vault are closed.
+#[event]
+pub struct ProjectClosedEvent {
+    /// The ID of the closed project.
+    pub project_id: u64,
+    /// The account that received the rent.
+    pub receiver: Pubkey,
+}
+
+/// Emitted when a project's lifecycle status changes.
+#[event]
+pub struct ProjectStatusUpdatedEvent {
+    /// The ID of the project.
+    pub project_id: u64,
+    /// The new status.
+    pub status: ProjectStatus,
+    /// Whether unstakes are free of the unstake fee in `Sunset`.
+    pub sunset_fee_waived: bool,
+}
+
+/// Emitted when a project authority transfer is started.
+#[event]
+pub struct ProjectAuthorityTransferStartedEvent {
+    /// The ID of the project.
+    pub project_id: u64,
+    /// The current authority of the project.
+    pub authority: Pubkey,
+    /// The authority that has to accept the transfer.
+    pub pending_authority: Pubkey,
+    /// The Unix timestamp after which the transfer can no longer be accepted.
+    pub expires_at: i64,
+}
+
+/// Emitted when a project authority transfer is accepted.
+#[event]
+pub struct ProjectAuthorityTransferredEvent {
+    /// The ID of the project.
+    pub project_id: u64,
+    /// The previous authority of the project.
+    pub previous_authority: Pubkey,
+    /// The new authority of the project.
+    pub new_authority: Pubkey,
+}
+
+/// Emitted when the platform or a project is paused or resumed.
+#[event]
+pub struct PauseUpdatedEvent {
+    /// The ID of the paused project, or `None` for the whole platform.
+    pub project_id: Option<u64>,
+    /// Whether new stakes and top-ups are blocked.
+    pub paused: bool,
+    /// Whether unstaking and withdrawals are blocked.
+    pub unstake_paused: bool,
+}
+
+/// Emitted when a sensitive administrative action is proposed.
+#[event]
+pub struct ProposalCreatedEvent {
+    /// The ID of the new proposal.
+    pub proposal_id: u64,
+    /// The authority that created the proposal.
+    pub proposer: Pubkey,
+    /// The proposed action.
+    pub action: ProposalAction,
+}
+
+/// Emitted when an authority approves a proposal.
+#[event]
+pub struct ProposalApprovedEvent {
+    /// The ID of the approved proposal.
+    pub proposal_id: u64,
+    /// The authority that approved.
+    pub approver: Pubkey,
+    /// The number of approvals collected so far.
+    pub approvals: u8,
+}
+
+/// Emitted when a key is nominated as platform authority.
+#[event]
+pub struct AuthorityNominatedEvent {
+    /// The nominated key.
+    pub nominee: Pubkey,
+    /// The roles it gets on acceptance.
+    pub roles: u8,
+    /// The Unix timestamp after which the nomination can no longer be accepted.
+    pub expires_at: i64,
+}
+
+/// Emitted when a nominee accepts and joins the platform authorities.
+#[event]
+pub struct AuthorityAddedEvent {
+    /// The new authority.
+    pub authority: Pubkey,
+    /// Its roles.
+    pub roles: u8,
+}
+
+/// Emitted when a proposal is executed.
+#[event]
+pub struct ProposalExecutedEvent {
+    /// The ID of the executed proposal.
+    pub proposal_id: u64,
+    /// The authority that executed it.
+    pub executor: Pubkey,
+}
+
+/// Emitted when a reward stream is funded or rescheduled.
+#[event]
+pub struct RewardsFundedEvent {
+    /// The ID of the project that owns the stream.
+    pub project_id: u64,
+    /// The mint of the stream's reward token.
+    pub reward_mint: Pubkey,
+    /// The amount of reward tokens deposited.
+    pub amount: u64,
+    /// The Unix timestamp at which emission starts.
+    pub start_timestamp: i64,
+    /// The Unix timestamp at which emission ends.
+    pub end_timestamp: i64,
+}
+
+/// Emitted when a reward stream's emission rate changes.
+#[event]
+pub struct RewardRateUpdatedEvent {
+    /// The ID of the project that owns the stream.
+    pub project_id: u64,
+    /// The mint of the stream's reward token.
+    pub reward_mint: Pubkey,
+    /// The new amount of reward tokens emitted per second.
+    pub reward_rate: u64,
+}
+
+/// Emitted when part of an emergency unstake fee is redistributed to the remaining stakers.
+#[event]
+pub struct PenaltyRedistributedEvent {
+    /// The ID of the project where the emergency unstake occurred.
+    pub project_id: u64,
+    /// The unique ID of the stake that paid the fee.
+    pub stake_id: u64,
+    /// The amount of the fee added to the remaining stakers' rewards.
+    pub amount: u64,
+}
+
 // ============== ERRORS ==============
 
 #[error_code]
@@ -843,7 +4378,7 @@ pub enum ErrorCode {
     NotPlatformAuthority,
     #[msg("The authority to add already exists.")]
     AuthorityAlreadyExists,
-    #[msg("Cannot remove the last authority.")]
+    #[msg("Cannot remove the last super admin.")]
     CannotRemoveLastAuthority,
     #[msg("The authority to remove was not found.")]
     AuthorityNotFound,
@@ -855,4 +4390,106 @@ pub enum ErrorCode {
     InvalidFeeBps,
     #[msg("Lockup period has already ended. Use the standard unstake function.")]
     LockupPeriodEnded,
+    #[msg("Arithmetic overflow.")]
+    MathOverflow,
+    #[msg("The project already has the maximum number of reward streams.")]
+    TooManyRewardStreams,
+    #[msg("Reward vault, user reward token account and token program are required for every reward stream.")]
+    MissingRewardAccounts,
+    #[msg("Duration multiplier must be between 10000 (1x) and 50000 (5x) basis points.")]
+    InvalidMultiplier,
+    #[msg("There are no rewards to claim.")]
+    NoRewardsToClaim,
+    #[msg("Reward schedule end must be after its start and in the future.")]
+    InvalidRewardSchedule,
+    #[msg("A reward account does not match the project's reward stream.")]
+    InvalidRewardAccount,
+    #[msg("The reward stream does not exist.")]
+    InvalidRewardStream,
+    #[msg("The reward schedule would emit more than the reward vault holds.")]
+    RewardScheduleUnderfunded,
+    #[msg("Partial unstake amount must be greater than zero and less than the staked amount. Use unstake to withdraw everything.")]
+    InvalidPartialAmount,
+    #[msg("The new lock-up must end later than the current unlock time.")]
+    LockNotExtended,
+    #[msg("Cooldown must be between zero and MAX_COOLDOWN_SECONDS.")]
+    InvalidCooldown,
+    #[msg("This project has an unbonding cooldown. Use request_unstake and withdraw instead.")]
+    CooldownRequired,
+    #[msg("The unbonding cooldown has not ended yet.")]
+    CooldownNotEnded,
+    #[msg("The stake is not in its unbonding cooldown.")]
+    NotCooling,
+    #[msg("The stake is still active. Unstake it before closing.")]
+    StakeStillActive,
+    #[msg("The stake is still cooling. Withdraw it before closing.")]
+    UnstakeStillCooling,
+    #[msg("Staking is paused.")]
+    StakingPaused,
+    #[msg("Unstaking is paused. Emergency unstake is still available.")]
+    UnstakingPaused,
+    #[msg("The signer does not hold the role required by this instruction.")]
+    MissingRole,
+    #[msg("Roles must be a non-empty combination of the known role bits.")]
+    InvalidRoles,
+    #[msg("This action needs an approved proposal while the approval threshold is above 1.")]
+    ProposalRequired,
+    #[msg("The signer has already approved this proposal.")]
+    AlreadyApproved,
+    #[msg("The proposal has reached the maximum number of approvals.")]
+    TooManyApprovals,
+    #[msg("The proposal does not have enough approvals.")]
+    ThresholdNotReached,
+    #[msg("The proposal has already been executed.")]
+    ProposalAlreadyExecuted,
+    #[msg("The project account does not match the proposal.")]
+    InvalidProposalProject,
+    #[msg("Approval threshold must be at least 1 and at most the number of super admins.")]
+    InvalidApprovalThreshold,
+    #[msg("There is no pending configuration change.")]
+    NoPendingConfig,
+    #[msg("The pending configuration change is not effective yet.")]
+    ConfigChangeNotReady,
+    #[msg("Signer is neither the project's authority nor a platform authority.")]
+    NotProjectAuthority,
+    #[msg("Signer is not the pending authority of the project.")]
+    NotPendingProjectAuthority,
+    #[msg("Signer has no pending authority nomination.")]
+    NominationNotFound,
+    #[msg("The nomination has expired.")]
+    NominationExpired,
+    #[msg("Stake amount is below the project's minimum.")]
+    StakeBelowMinimum,
+    #[msg("Stake amount is above the project's maximum.")]
+    StakeAboveMaximum,
+    #[msg("The user's total stake in the project would exceed its per-user cap.")]
+    UserStakeCapExceeded,
+    #[msg("The minimum stake amount cannot exceed the maximum.")]
+    InvalidStakeLimits,
+    #[msg("The project's total stake would exceed its cap.")]
+    TotalStakeCapExceeded,
+    #[msg("The project's staking window has not opened yet.")]
+    StakingNotStarted,
+    #[msg("The project's staking window has closed.")]
+    StakingEnded,
+    #[msg("The staking window must end after it starts.")]
+    InvalidStakeWindow,
+    #[msg("The project no longer accepts deposits.")]
+    DepositsClosed,
+    #[msg("The project is being wound down.")]
+    ProjectSunset,
+    #[msg("The project still has staked tokens.")]
+    ProjectNotEmpty,
+    #[msg("The project vault still holds tokens.")]
+    VaultNotEmpty,
+    #[msg("The vault holds no tokens beyond the staked total.")]
+    NoSurplus,
+    #[msg("The change would leave fewer super admins or fee managers than the approval threshold.")]
+    ApproversBelowThreshold,
+    #[msg("The user still has tokens staked in the project.")]
+    UserProjectNotEmpty,
+    #[msg("A reward vault still holds tokens.")]
+    RewardVaultNotEmpty,
+    #[msg("The stake still has unclaimed rewards. Claim them before closing the position.")]
+    RewardsStillOwed,
 }
@@ -1,7 +1,7 @@
 import * as anchor from "@coral-xyz/anchor";
 import { Program } from "@coral-xyz/anchor";
 import { AimStakingProgramV2 } from "../target/types/aim_staking_program_v2";
-import { TOKEN_PROGRAM_ID, createMint, createAssociatedTokenAccount, mintTo, getAccount, TOKEN_2022_PROGRAM_ID } from "@solana/spl-token";
+import { TOKEN_PROGRAM_ID, createMint, createAssociatedTokenAccount, getOrCreateAssociatedTokenAccount, mintTo, getAccount, TOKEN_2022_PROGRAM_ID } from "@solana/spl-token";
 import { assert } from "chai";
 
 describe("aim_staking_program_v2", () => {
@@ -27,6 +27,12 @@ describe("aim_staking_program_v2", () => {
     Uint8Array.from([193,209,44,152,172,165,106,185,158,23,176,152,117,171,99,230,145,217,168,224,4,23,88,103,126,128,166,230,231,244,56,104,226,43,43,187,62,154,98,132,85,177,127,160,249,115,76,60,202,166,2,23,89,97,170,150,89,107,185,138,162,6,70,189])
   );
 
+  // Role bits of platform authorities
+  const ROLE_SUPER_ADMIN = 1 << 0;
+  const ROLE_PROJECT_MANAGER = 1 << 1;
+  const ROLE_FEE_MANAGER = 1 << 2;
+  const ROLE_PAUSER = 1 << 3;
+
   // Helper function to sleep
   const sleep = (ms: number) => new Promise(resolve => setTimeout(resolve, ms));
 
@@ -46,6 +52,7 @@ describe("aim_staking_program_v2", () => {
       let vaultAuthorityPda: anchor.web3.PublicKey;
       let stakeInfoPda: anchor.web3.PublicKey;
       let newAuthorityProjectConfigPda: anchor.web3.PublicKey;
+      let coolingProjectConfigPda: anchor.web3.PublicKey;
 
       // To manage multiple stakes
       const stakes: {
@@ -55,6 +62,59 @@ describe("aim_staking_program_v2", () => {
         duration: number
       }[] = [];
 
+      // Reward streams of the main project, in stream order.
+      // Each stream is paid out with [rewardVault, userRewardTokenAccount, rewardMint, tokenProgram] remaining accounts.
+      // Streams left out are skipped.
+      const rewardStreams: {
+        mint: anchor.web3.PublicKey,
+        vault: anchor.web3.PublicKey,
+        userTokenAccount: anchor.web3.PublicKey,
+      }[] = [];
+
+      const rewardRemainingAccounts = () => rewardStreams.flatMap(stream => [
+        { pubkey: stream.vault, isWritable: true, isSigner: false },
+        { pubkey: stream.userTokenAccount, isWritable: true, isSigner: false },
+        { pubkey: stream.mint, isWritable: false, isSigner: false },
+        { pubkey: tokenProgram, isWritable: false, isSigner: false },
+      ]);
+
+      const findStakePdas = async (stakeId: anchor.BN) => {
+        const [stakeInfoPda] = await anchor.web3.PublicKey.findProgramAddress(
+          [Buffer.from("stake"), projectConfigPda.toBuffer(), user.publicKey.toBuffer(), stakeId.toBuffer('le', 8)],
+          program.programId
+        );
+        const [unstakeInfoPda] = await anchor.web3.PublicKey.findProgramAddress(
+          [Buffer.from("unstake"), stakeInfoPda.toBuffer()],
+          program.programId
+        );
+        return { stakeInfoPda, unstakeInfoPda };
+      };
+
+      const findUserProjectPda = () =>
+        anchor.web3.PublicKey.findProgramAddressSync(
+          [Buffer.from("user-project"), projectConfigPda.toBuffer(), user.publicKey.toBuffer()],
+          program.programId
+        )[0];
+
+      // Stakes into the main project and returns the stake's PDAs
+      const stakeTokens = async (amount: anchor.BN, durationDays: number, stakeId: anchor.BN, autoRenew = false) => {
+        const pdas = await findStakePdas(stakeId);
+        await (program.methods.stake as any)(amount, durationDays, stakeId, autoRenew)
+          .accounts({
+            projectConfig: projectConfigPda,
+            stakeInfo: pdas.stakeInfoPda,
+            unstakeInfo: pdas.unstakeInfoPda,
+            user: user.publicKey,
+            userTokenAccount: userTokenAccount,
+            vault: vaultPda,
+            systemProgram: anchor.web3.SystemProgram.programId,
+            tokenProgram: tokenProgram,
+          })
+          .signers([user])
+          .rpc();
+        return pdas;
+      };
+
       before(async () => {
         // On devnet/testnet, airdrops can be unreliable. It's better to fund these accounts manually.
         console.log(`User public key: ${user.publicKey.toBase58()}`);
@@ -132,7 +192,7 @@ describe("aim_staking_program_v2", () => {
           const existingPlatformConfig = await program.account.platformConfig.fetch(platformConfigPda);
           console.log("Platform already initialized, skipping initialization");
           // If platform exists, verify the initializer is an authority
-          assert.ok(existingPlatformConfig.authorities.some(auth => auth.equals(authority)));
+          assert.ok(existingPlatformConfig.authorities.some(auth => auth.authority.equals(authority)));
           return;
         } catch (error) {
           // Platform doesn't exist, proceed with initialization
@@ -153,7 +213,8 @@ describe("aim_staking_program_v2", () => {
 
         const platformConfigAccount = await program.account.platformConfig.fetch(platformConfigPda);
         assert.equal(platformConfigAccount.authorities.length, 1);
-        assert.ok(platformConfigAccount.authorities[0].equals(authority));
+        assert.ok(platformConfigAccount.authorities[0].authority.equals(authority));
+        assert.equal(platformConfigAccount.authorities[0].roles, ROLE_SUPER_ADMIN | ROLE_PROJECT_MANAGER | ROLE_FEE_MANAGER | ROLE_PAUSER);
         assert.equal(platformConfigAccount.projectCount.toNumber(), 0);
       });
 
@@ -203,7 +264,12 @@ describe("aim_staking_program_v2", () => {
         console.log("registerProject accounts:", JSON.stringify(accounts, (key, value) => (value?.toBase58 ? value.toBase58() : value), 2));
 
         const projectName = "My Test Project";
-        const allowedDurations = [1, 7, 30]; // e.g., 1 day, 7 days, 30 days
+        // e.g., 1 day, 7 days, 30 days, with longer locks earning a higher reward multiplier
+        const allowedDurations = [
+          { durationDays: 1, multiplierBps: 10000 },
+          { durationDays: 7, multiplierBps: 12000 },
+          { durationDays: 30, multiplierBps: 15000 },
+        ];
         console.log("registerProject params:", { projectName, allowedDurations });
         const txid_register = await program.methods
           .registerProject(projectName, allowedDurations)
@@ -226,7 +292,13 @@ describe("aim_staking_program_v2", () => {
       });
 
       it("Updates allowed durations", async () => {
-        const newAllowedDurations = [0, 14, 30, 90]; // Added 0 for testing unstake
+        // Added 0 for testing unstake
+        const newAllowedDurations = [
+          { durationDays: 0, multiplierBps: 10000 },
+          { durationDays: 14, multiplierBps: 12500 },
+          { durationDays: 30, multiplierBps: 15000 },
+          { durationDays: 90, multiplierBps: 20000 },
+        ];
         const accounts = {
           platformConfig: platformConfigPda,
           projectConfig: projectConfigPda,
@@ -245,6 +317,24 @@ describe("aim_staking_program_v2", () => {
         assert.deepEqual(projectConfig.allowedDurations, newAllowedDurations);
       });
 
+      it("Fails to update allowed durations with a multiplier below 1x", async () => {
+        const accounts = {
+          platformConfig: platformConfigPda,
+          projectConfig: projectConfigPda,
+          authority: authority,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        };
+        try {
+          await program.methods
+            .updateAllowedDurations([{ durationDays: 7, multiplierBps: 5000 }])
+            .accountsStrict(accounts)
+            .rpc();
+          assert.fail("Should have failed with a multiplier below 1x.");
+        } catch (error) {
+          assert.include(error.toString(), "InvalidMultiplier");
+        }
+      });
+
       it("Fails to stake with a non-allowed duration", async () => {
         const amountToStake = new anchor.BN(10 * 10 ** 9);
         const nonAllowedDuration = 5; // This duration is not in [14, 30, 90]
@@ -275,7 +365,7 @@ describe("aim_staking_program_v2", () => {
         console.log("stake (fail) params:", { amountToStake: amountToStake.toString(), nonAllowedDuration, stakeId: stakeId.toString() });
 
         try {
-            await (program.methods.stake as any)(amountToStake, nonAllowedDuration, stakeId)
+            await (program.methods.stake as any)(amountToStake, nonAllowedDuration, stakeId, false)
                 .accounts(accounts)
                 .signers([user])
                 .rpc();
@@ -286,28 +376,45 @@ describe("aim_staking_program_v2", () => {
       });
 
       describe("Authority Management", () => {
-        it("Fails to add an authority using a non-authority account", async () => {
+        it("Fails to nominate an authority using a non-authority account", async () => {
             const accounts = {
                 platformConfig: platformConfigPda,
                 authority: newAuthority.publicKey,
                 systemProgram: anchor.web3.SystemProgram.programId,
             };
-            console.log("addAuthority (fail) accounts:", JSON.stringify(accounts, (key, value) => (value?.toBase58 ? value.toBase58() : value), 2));
-            console.log("addAuthority (fail) params:", { newAuthority: authority.toBase58() });
+            console.log("nominateAuthority (fail) accounts:", JSON.stringify(accounts, (key, value) => (value?.toBase58 ? value.toBase58() : value), 2));
+            console.log("nominateAuthority (fail) params:", { nominee: authority.toBase58() });
             try {
                 await program.methods
-                    .addAuthority(authority)
+                    .nominateAuthority(authority, ROLE_PROJECT_MANAGER)
                     .accountsStrict(accounts)
                     .signers([newAuthority])
                     .rpc();
-                assert.fail("Should have failed to add authority with a non-authority key.");
+                assert.fail("Should have failed to nominate an authority with a non-authority key.");
             } catch (error) {
                 // Anchor v0.29.0 wraps the error, so we need to check the inner message
                 assert.include(error.toString(), "NotPlatformAuthority");
             }
         });
 
-        it("Adds a new authority", async () => {
+        it("Fails to accept without a nomination", async () => {
+            try {
+                await program.methods
+                    .acceptAuthority()
+                    .accountsStrict({
+                        platformConfig: platformConfigPda,
+                        nominee: newAuthority.publicKey,
+                        systemProgram: anchor.web3.SystemProgram.programId,
+                    })
+                    .signers([newAuthority])
+                    .rpc();
+                assert.fail("Accepting without a nomination should have failed.");
+            } catch (error) {
+                assert.include(error.toString(), "NominationNotFound");
+            }
+        });
+
+        it("Adds a new authority once it accepts its nomination", async () => {
             const platformConfigBefore = await program.account.platformConfig.fetch(platformConfigPda);
             const authorityCountBefore = platformConfigBefore.authorities.length;
 
@@ -316,17 +423,34 @@ describe("aim_staking_program_v2", () => {
                 authority: authority,
                 systemProgram: anchor.web3.SystemProgram.programId,
             };
-            console.log("addAuthority accounts:", JSON.stringify(accounts, (key, value) => (value?.toBase58 ? value.toBase58() : value), 2));
-            console.log("addAuthority params:", { newAuthority: newAuthority.publicKey.toBase58() });
+            console.log("nominateAuthority accounts:", JSON.stringify(accounts, (key, value) => (value?.toBase58 ? value.toBase58() : value), 2));
+            console.log("nominateAuthority params:", { nominee: newAuthority.publicKey.toBase58() });
 
             await program.methods
-                .addAuthority(newAuthority.publicKey)
+                .nominateAuthority(newAuthority.publicKey, ROLE_PROJECT_MANAGER | ROLE_FEE_MANAGER)
                 .accountsStrict(accounts)
                 .rpc();
 
+            // Not an authority until the nominee accepts
+            const platformConfigNominated = await program.account.platformConfig.fetch(platformConfigPda);
+            assert.equal(platformConfigNominated.authorities.length, authorityCountBefore);
+            const nomination = platformConfigNominated.nominations.find(n => n.nominee.equals(newAuthority.publicKey));
+            assert.equal(nomination.roles, ROLE_PROJECT_MANAGER | ROLE_FEE_MANAGER);
+
+            await program.methods
+                .acceptAuthority()
+                .accountsStrict({
+                    platformConfig: platformConfigPda,
+                    nominee: newAuthority.publicKey,
+                    systemProgram: anchor.web3.SystemProgram.programId,
+                })
+                .signers([newAuthority])
+                .rpc();
+
             const platformConfigAfter = await program.account.platformConfig.fetch(platformConfigPda);
             assert.equal(platformConfigAfter.authorities.length, authorityCountBefore + 1);
-            assert.ok(platformConfigAfter.authorities.some(auth => auth.equals(newAuthority.publicKey)));
+            const added = platformConfigAfter.authorities.find(auth => auth.authority.equals(newAuthority.publicKey));
+            assert.equal(added.roles, ROLE_PROJECT_MANAGER | ROLE_FEE_MANAGER);
         });
 
         it("New authority can register a project", async () => {
@@ -361,7 +485,11 @@ describe("aim_staking_program_v2", () => {
             };
             
             const projectName = "Project by New Authority";
-            const allowedDurations = [1, 2, 3];
+            const allowedDurations = [
+              { durationDays: 1, multiplierBps: 10000 },
+              { durationDays: 2, multiplierBps: 10000 },
+              { durationDays: 3, multiplierBps: 10000 },
+            ];
             console.log("registerProject (new authority) accounts:", JSON.stringify(accounts, (key, value) => (value?.toBase58 ? value.toBase58() : value), 2));
             console.log("registerProject (new authority) params:", { projectName, allowedDurations });
             await program.methods
@@ -377,6 +505,37 @@ describe("aim_staking_program_v2", () => {
             assert.equal(projectConfigAccount.name, projectName);
         });
 
+        it("Authority without the Pauser role cannot pause the platform", async () => {
+            try {
+                await program.methods
+                    .setPaused(true, true)
+                    .accountsStrict({
+                        platformConfig: platformConfigPda,
+                        projectConfig: null,
+                        authority: newAuthority.publicKey,
+                    })
+                    .signers([newAuthority])
+                    .rpc();
+                assert.fail("An authority without the Pauser role should not be able to pause.");
+            } catch (error) {
+                assert.include(error.toString(), "MissingRole");
+            }
+        });
+
+        it("Updates the roles of an authority", async () => {
+            await program.methods
+                .setAuthorityRoles(newAuthority.publicKey, ROLE_PAUSER)
+                .accountsStrict({
+                    platformConfig: platformConfigPda,
+                    authority: authority,
+                })
+                .rpc();
+
+            const platformConfig = await program.account.platformConfig.fetch(platformConfigPda);
+            const updated = platformConfig.authorities.find(auth => auth.authority.equals(newAuthority.publicKey));
+            assert.equal(updated.roles, ROLE_PAUSER);
+        });
+
         it("Removes an authority", async () => {
             const platformConfigBefore = await program.account.platformConfig.fetch(platformConfigPda);
             const authorityCountBefore = platformConfigBefore.authorities.length;
@@ -396,7 +555,7 @@ describe("aim_staking_program_v2", () => {
 
             const platformConfigAfter = await program.account.platformConfig.fetch(platformConfigPda);
             assert.equal(platformConfigAfter.authorities.length, authorityCountBefore - 1);
-            assert.isFalse(platformConfigAfter.authorities.some(auth => auth.equals(newAuthority.publicKey)));
+            assert.isFalse(platformConfigAfter.authorities.some(auth => auth.authority.equals(newAuthority.publicKey)));
         });
 
         it("Removed authority cannot register a project", async () => {
@@ -432,7 +591,11 @@ describe("aim_staking_program_v2", () => {
             
             try {
                 const projectName = "Project by Removed Authority";
-                const allowedDurations = [4, 5, 6];
+                const allowedDurations = [
+                  { durationDays: 4, multiplierBps: 10000 },
+                  { durationDays: 5, multiplierBps: 10000 },
+                  { durationDays: 6, multiplierBps: 10000 },
+                ];
                 console.log("registerProject (removed authority) accounts:", JSON.stringify(accounts, (key, value) => (value?.toBase58 ? value.toBase58() : value), 2));
                 console.log("registerProject (removed authority) params:", { projectName, allowedDurations });
                 await program.methods
@@ -446,13 +609,27 @@ describe("aim_staking_program_v2", () => {
             }
         });
 
+        it("Takes the project back from the removed authority", async () => {
+            await program.methods
+                .reassignProjectAuthority(authority)
+                .accountsStrict({
+                    platformConfig: platformConfigPda,
+                    projectConfig: newAuthorityProjectConfigPda,
+                    authority: authority,
+                })
+                .rpc();
+
+            const projectConfig = await program.account.projectConfig.fetch(newAuthorityProjectConfigPda);
+            assert.ok(projectConfig.authority.equals(authority));
+            assert.isNull(projectConfig.pendingAuthority);
+        });
+
         it("Removed authority cannot update project config", async () => {
             const accounts = {
                 platformConfig: platformConfigPda,
                 projectConfig: newAuthorityProjectConfigPda,
                 authority: newAuthority.publicKey,
             };
-            console.log("updateProjectConfig (removed authority) accounts:", JSON.stringify(accounts, (key, value) => (value?.toBase58 ? value.toBase58() : value), 2));
             try {
                 await program.methods
                     .updateProjectConfig(feeWallet.publicKey, 500, 500)
@@ -461,7 +638,26 @@ describe("aim_staking_program_v2", () => {
                     .rpc();
                 assert.fail("Removed authority should not be able to update project config.");
             } catch (error) {
-                assert.include(error.toString(), "NotPlatformAuthority");
+                assert.include(error.toString(), "NotProjectAuthority");
+            }
+        });
+
+        it("Non-owner cannot update project config", async () => {
+            const accounts = {
+                platformConfig: platformConfigPda,
+                projectConfig: newAuthorityProjectConfigPda,
+                authority: user.publicKey,
+            };
+            console.log("updateProjectConfig (non-owner) accounts:", JSON.stringify(accounts, (key, value) => (value?.toBase58 ? value.toBase58() : value), 2));
+            try {
+                await program.methods
+                    .updateProjectConfig(feeWallet.publicKey, 500, 500)
+                    .accountsStrict(accounts)
+                    .signers([user])
+                    .rpc();
+                assert.fail("A non-owner should not be able to update project config.");
+            } catch (error) {
+                assert.include(error.toString(), "NotProjectAuthority");
             }
         });
 
@@ -469,7 +665,7 @@ describe("aim_staking_program_v2", () => {
             const platformConfig = await program.account.platformConfig.fetch(platformConfigPda);
             // In our test flow, there should only be one authority left.
             assert.equal(platformConfig.authorities.length, 1);
-            const lastAuthority = platformConfig.authorities[0];
+            const lastAuthority = platformConfig.authorities[0].authority;
 
             const accounts = {
                 platformConfig: platformConfigPda,
@@ -545,7 +741,7 @@ describe("aim_staking_program_v2", () => {
         console.log("stake (1st) accounts:", JSON.stringify(stakeAccounts, (key, value) => (value?.toBase58 ? value.toBase58() : value), 2));
         console.log("stake (1st) params:", { amountToStake: amountToStake.toString(), durationDays, stakeId: stakeId.toString() });
 
-        await (program.methods.stake as any)(amountToStake, durationDays, stakeId)
+        await (program.methods.stake as any)(amountToStake, durationDays, stakeId, false)
           .accounts(stakeAccounts)
           .signers([user])
           .rpc();
@@ -556,6 +752,8 @@ describe("aim_staking_program_v2", () => {
         assert.equal(stakeInfoAccount.durationDays, durationDays);
         assert.equal((stakeInfoAccount as any).stakeId.toString(), stakeId.toString());
         assert.isTrue(stakeInfoAccount.isStaked);
+        // 14-day stakes carry a 1.25x multiplier
+        assert.equal(stakeInfoAccount.weight.toString(), amountToStake.muln(12500).divn(10000).toString());
 
         const vaultAccount = await getAccount(provider.connection, vaultPda, undefined, tokenProgram);
         assert.equal(vaultAccount.amount.toString(), amountToStake.toString());
@@ -593,7 +791,7 @@ describe("aim_staking_program_v2", () => {
         console.log("stake (2nd) accounts:", JSON.stringify(stakeAccounts, (key, value) => (value?.toBase58 ? value.toBase58() : value), 2));
         console.log("stake (2nd) params:", { amountToStake: amountToStake.toString(), durationDays, stakeId: stakeId.toString() });
 
-        await (program.methods.stake as any)(amountToStake, durationDays, stakeId)
+        await (program.methods.stake as any)(amountToStake, durationDays, stakeId, false)
           .accounts(stakeAccounts)
           .signers([user])
           .rpc();
@@ -629,7 +827,7 @@ describe("aim_staking_program_v2", () => {
             program.programId
         );
 
-        await (program.methods.stake as any)(amountToStake, durationDays, stakeId)
+        await (program.methods.stake as any)(amountToStake, durationDays, stakeId, false)
             .accounts({
                 projectConfig: projectConfigPda,
                 stakeInfo: stakeInfoPda,
@@ -659,6 +857,7 @@ describe("aim_staking_program_v2", () => {
             unstakeInfo: unstakeInfoPda,
             user: user.publicKey,
             userTokenAccount: userTokenAccount,
+            tokenMint: tokenMint,
             vault: vaultPda,
             vaultAuthority: vaultAuthorityPda,
             feeWallet: feeWalletTokenAccount,
@@ -717,14 +916,17 @@ describe("aim_staking_program_v2", () => {
         const feeWalletAccountBefore = await getAccount(provider.connection, feeWalletTokenAccount, undefined, tokenProgram);
 
         const emergencyUnstakeAccounts = {
+          platformConfig: platformConfigPda,
           projectConfig: projectConfigPda,
           stakeInfo: stakeToUnstake.pda,
           unstakeInfo: unstakeInfoPda,
           user: user.publicKey,
           userTokenAccount: userTokenAccount,
+          tokenMint: tokenMint,
           vault: vaultPda,
           vaultAuthority: vaultAuthorityPda,
           feeWallet: feeWalletTokenAccount,
+          penaltyRewardVault: null,
           tokenProgram: tokenProgram,
           systemProgram: anchor.web3.SystemProgram.programId,
         };
@@ -787,7 +989,7 @@ describe("aim_staking_program_v2", () => {
             [Buffer.from("unstake"), stakeInfoPda.toBuffer()],
             program.programId
         );
-        await (program.methods.stake as any)(amountToStake, durationDays, stakeId)
+        await (program.methods.stake as any)(amountToStake, durationDays, stakeId, false)
             .accounts({
                 projectConfig: projectConfigPda,
                 stakeInfo: stakeInfoPda,
@@ -811,14 +1013,17 @@ describe("aim_staking_program_v2", () => {
         );
 
         const emergencyUnstakeAccounts = {
+          platformConfig: platformConfigPda,
           projectConfig: projectConfigPda,
           stakeInfo: stakeInfoPda,
           unstakeInfo: unstakeInfoPda,
           user: user.publicKey,
           userTokenAccount: userTokenAccount,
+          tokenMint: tokenMint,
           vault: vaultPda,
           vaultAuthority: vaultAuthorityPda,
           feeWallet: feeWalletTokenAccount,
+          penaltyRewardVault: null,
           tokenProgram: tokenProgram,
           systemProgram: anchor.web3.SystemProgram.programId,
         };
@@ -833,6 +1038,1881 @@ describe("aim_staking_program_v2", () => {
           assert.include(error.toString(), "LockupPeriodEnded");
         }
       });
+
+      describe("Rewards", () => {
+        let rewardMint: anchor.web3.PublicKey;
+        let userRewardTokenAccount: anchor.web3.PublicKey;
+        const rewardRate = new anchor.BN(1000);
+        const rewardFunding = new anchor.BN(1000 * 10 ** 9);
+
+        const addRewardStream = async (mint: anchor.web3.PublicKey, userTokenAccount: anchor.web3.PublicKey) => {
+          const projectConfig = await program.account.projectConfig.fetch(projectConfigPda);
+          const [rewardVaultPda] = await anchor.web3.PublicKey.findProgramAddress(
+            [Buffer.from("reward-vault"), projectConfig.projectId.toBuffer('le', 8), Buffer.from([projectConfig.rewardStreamCount])],
+            program.programId
+          );
+          const streamIndex = projectConfig.rewardStreamCount;
+          const now = Math.floor(Date.now() / 1000);
+
+          const accounts = {
+            platformConfig: platformConfigPda,
+            projectConfig: projectConfigPda,
+            rewardMint: mint,
+            rewardVault: rewardVaultPda,
+            vaultAuthority: vaultAuthorityPda,
+            authority: authority,
+            systemProgram: anchor.web3.SystemProgram.programId,
+            rewardTokenProgram: tokenProgram,
+            rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+          };
+          console.log("addRewardStream accounts:", JSON.stringify(accounts, (key, value) => (value?.toBase58 ? value.toBase58() : value), 2));
+
+          await program.methods
+            .addRewardStream()
+            .accountsStrict(accounts)
+            .rpc();
+
+          // Deposit the rewards from the authority's token account and open a one-day window
+          const funderTokenAccount = await getOrCreateAssociatedTokenAccount(
+            provider.connection,
+            (provider.wallet as any).payer,
+            mint,
+            authority,
+            false,
+            undefined,
+            undefined,
+            tokenProgram
+          );
+          await mintTo(
+            provider.connection,
+            (provider.wallet as any).payer,
+            mint,
+            funderTokenAccount.address,
+            authority,
+            rewardFunding.toNumber(),
+            [],
+            undefined,
+            tokenProgram
+          );
+          await program.methods
+            .fundRewards(streamIndex, rewardFunding, new anchor.BN(now - 60), new anchor.BN(now + 24 * 60 * 60))
+            .accountsStrict({
+              platformConfig: platformConfigPda,
+              projectConfig: projectConfigPda,
+              rewardVault: rewardVaultPda,
+              rewardMint: mint,
+              funderTokenAccount: funderTokenAccount.address,
+              authority: authority,
+              rewardTokenProgram: tokenProgram,
+            })
+            .rpc();
+
+          await program.methods
+            .updateRewardRate(streamIndex, rewardRate)
+            .accountsStrict({
+              platformConfig: platformConfigPda,
+              projectConfig: projectConfigPda,
+              rewardVault: rewardVaultPda,
+              authority: authority,
+            })
+            .rpc();
+
+          rewardStreams.push({ mint, vault: rewardVaultPda, userTokenAccount });
+        };
+
+        before(async () => {
+          rewardMint = await createMint(
+            provider.connection,
+            (provider.wallet as any).payer,
+            authority,
+            null,
+            9,
+            undefined,
+            undefined,
+            tokenProgram
+          );
+          userRewardTokenAccount = await createAssociatedTokenAccount(
+            provider.connection,
+            (provider.wallet as any).payer,
+            rewardMint,
+            user.publicKey,
+            {},
+            tokenProgram
+          );
+        });
+
+        it("Adds a partner token reward stream", async () => {
+          await addRewardStream(rewardMint, userRewardTokenAccount);
+
+          const projectConfig = await program.account.projectConfig.fetch(projectConfigPda);
+          assert.equal(projectConfig.rewardStreamCount, 1);
+          assert.ok(projectConfig.rewardStreams[0].mint.equals(rewardMint));
+          assert.ok(projectConfig.rewardStreams[0].vault.equals(rewardStreams[0].vault));
+          assert.ok(projectConfig.rewardStreams[0].tokenProgram.equals(tokenProgram));
+          assert.equal(projectConfig.rewardStreams[0].rewardRate.toString(), rewardRate.toString());
+
+          const rewardVaultAccount = await getAccount(provider.connection, rewardStreams[0].vault, undefined, tokenProgram);
+          assert.equal(rewardVaultAccount.amount.toString(), rewardFunding.toString());
+        });
+
+        it("Fails to set a reward rate the vault cannot cover", async () => {
+          // One day of emission at this rate is far more than the funded amount
+          const excessiveRate = rewardFunding;
+          try {
+            await program.methods
+              .updateRewardRate(0, excessiveRate)
+              .accountsStrict({
+                platformConfig: platformConfigPda,
+                projectConfig: projectConfigPda,
+                rewardVault: rewardStreams[0].vault,
+                authority: authority,
+              })
+              .rpc();
+            assert.fail("Should have failed with an underfunded reward schedule.");
+          } catch (error) {
+            assert.include(error.toString(), "RewardScheduleUnderfunded");
+          }
+        });
+
+        it("Adds a staked token reward stream", async () => {
+          await addRewardStream(tokenMint, userTokenAccount);
+
+          const projectConfig = await program.account.projectConfig.fetch(projectConfigPda);
+          assert.equal(projectConfig.rewardStreamCount, 2);
+          assert.ok(projectConfig.rewardStreams[1].mint.equals(tokenMint));
+        });
+
+        it("Pays accrued rewards on unstake", async () => {
+          const amountToStake = new anchor.BN(10 * 10 ** 9);
+          const durationDays = 0;
+          const stakeId = new anchor.BN(5 + suiteIndex * 100);
+
+          const [stakeInfoPda] = await anchor.web3.PublicKey.findProgramAddress(
+            [Buffer.from("stake"), projectConfigPda.toBuffer(), user.publicKey.toBuffer(), stakeId.toBuffer('le', 8)],
+            program.programId
+          );
+          const [unstakeInfoPda] = await anchor.web3.PublicKey.findProgramAddress(
+            [Buffer.from("unstake"), stakeInfoPda.toBuffer()],
+            program.programId
+          );
+
+          await (program.methods.stake as any)(amountToStake, durationDays, stakeId, false)
+            .accounts({
+              projectConfig: projectConfigPda,
+              stakeInfo: stakeInfoPda,
+              unstakeInfo: unstakeInfoPda,
+              user: user.publicKey,
+              userTokenAccount: userTokenAccount,
+              vault: vaultPda,
+              systemProgram: anchor.web3.SystemProgram.programId,
+              tokenProgram: tokenProgram,
+            })
+            .signers([user])
+            .rpc();
+
+          // Let some rewards accrue
+          await sleep(2000);
+
+          const userRewardAccountBefore = await getAccount(provider.connection, userRewardTokenAccount, undefined, tokenProgram);
+
+          await program.methods.unstake(stakeId)
+            .accounts({
+              projectConfig: projectConfigPda,
+              stakeInfo: stakeInfoPda,
+              unstakeInfo: unstakeInfoPda,
+              user: user.publicKey,
+              userTokenAccount: userTokenAccount,
+              tokenMint: tokenMint,
+              vault: vaultPda,
+              vaultAuthority: vaultAuthorityPda,
+              feeWallet: feeWalletTokenAccount,
+              tokenProgram: tokenProgram,
+              systemProgram: anchor.web3.SystemProgram.programId,
+            })
+            .remainingAccounts(rewardRemainingAccounts())
+            .signers([user])
+            .rpc();
+
+          const userRewardAccountAfter = await getAccount(provider.connection, userRewardTokenAccount, undefined, tokenProgram);
+          assert.isTrue(userRewardAccountAfter.amount > userRewardAccountBefore.amount);
+
+          const stakeInfoAccount = await program.account.userStakeInfo.fetch(stakeInfoPda);
+          assert.isFalse(stakeInfoAccount.isStaked);
+          assert.isTrue(stakeInfoAccount.rewardsOwed.every(owed => owed.toString() === "0"));
+        });
+
+        it("Keeps the rewards of streams left out of an unstake claimable", async () => {
+          const amountToStake = new anchor.BN(10 * 10 ** 9);
+          const durationDays = 0;
+          const stakeId = new anchor.BN(6 + suiteIndex * 100);
+
+          const [stakeInfoPda] = await anchor.web3.PublicKey.findProgramAddress(
+            [Buffer.from("stake"), projectConfigPda.toBuffer(), user.publicKey.toBuffer(), stakeId.toBuffer('le', 8)],
+            program.programId
+          );
+          const [unstakeInfoPda] = await anchor.web3.PublicKey.findProgramAddress(
+            [Buffer.from("unstake"), stakeInfoPda.toBuffer()],
+            program.programId
+          );
+
+          await (program.methods.stake as any)(amountToStake, durationDays, stakeId, false)
+            .accounts({
+              projectConfig: projectConfigPda,
+              stakeInfo: stakeInfoPda,
+              unstakeInfo: unstakeInfoPda,
+              user: user.publicKey,
+              userTokenAccount: userTokenAccount,
+              vault: vaultPda,
+              systemProgram: anchor.web3.SystemProgram.programId,
+              tokenProgram: tokenProgram,
+            })
+            .signers([user])
+            .rpc();
+
+          // Let some rewards accrue
+          await sleep(2000);
+
+          // Only the partner token stream is paid; the staked token stream is left out
+          await program.methods.unstake(stakeId)
+            .accounts({
+              projectConfig: projectConfigPda,
+              stakeInfo: stakeInfoPda,
+              unstakeInfo: unstakeInfoPda,
+              user: user.publicKey,
+              userTokenAccount: userTokenAccount,
+              tokenMint: tokenMint,
+              vault: vaultPda,
+              vaultAuthority: vaultAuthorityPda,
+              feeWallet: feeWalletTokenAccount,
+              tokenProgram: tokenProgram,
+              systemProgram: anchor.web3.SystemProgram.programId,
+            })
+            .remainingAccounts(rewardRemainingAccounts().slice(0, 4))
+            .signers([user])
+            .rpc();
+
+          const stakeInfoAccount = await program.account.userStakeInfo.fetch(stakeInfoPda);
+          assert.isFalse(stakeInfoAccount.isStaked);
+          assert.equal(stakeInfoAccount.weight.toString(), "0");
+          assert.isTrue(stakeInfoAccount.rewardsClaimed[0].gtn(0));
+          assert.equal(stakeInfoAccount.rewardsClaimed[1].toString(), "0");
+          const owed = stakeInfoAccount.rewardsOwed[1];
+          assert.isTrue(owed.gtn(0));
+
+          // The position cannot be closed before the owed rewards are claimed
+          try {
+            await program.methods.closePosition(stakeId)
+              .accountsStrict({
+                projectConfig: projectConfigPda,
+                stakeInfo: stakeInfoPda,
+                unstakeInfo: unstakeInfoPda,
+                user: user.publicKey,
+              })
+              .signers([user])
+              .rpc();
+            assert.fail("Closing a position with owed rewards should have failed.");
+          } catch (error) {
+            assert.include(error.toString(), "RewardsStillOwed");
+          }
+
+          // The left-out stream is claimed after the exit, without accruing anything more
+          await sleep(1000);
+          const balanceBefore = await getAccount(provider.connection, rewardStreams[1].userTokenAccount, undefined, tokenProgram);
+          await program.methods.claimRewards(stakeId)
+            .accountsStrict({
+              projectConfig: projectConfigPda,
+              stakeInfo: stakeInfoPda,
+              user: user.publicKey,
+              vaultAuthority: vaultAuthorityPda,
+            })
+            .remainingAccounts(rewardRemainingAccounts())
+            .signers([user])
+            .rpc();
+          const balanceAfter = await getAccount(provider.connection, rewardStreams[1].userTokenAccount, undefined, tokenProgram);
+          assert.equal((balanceAfter.amount - balanceBefore.amount).toString(), owed.toString());
+          const stakeInfoAfter = await program.account.userStakeInfo.fetch(stakeInfoPda);
+          assert.isTrue(stakeInfoAfter.rewardsOwed.every(amount => amount.toString() === "0"));
+        });
+
+        it("Claims rewards from every stream without unstaking", async () => {
+          const activeStake = stakes[1]; // The 30-day stake is still locked
+          const balancesBefore = await Promise.all(rewardStreams.map(stream =>
+            getAccount(provider.connection, stream.userTokenAccount, undefined, tokenProgram)
+          ));
+          const vaultAccountBefore = await getAccount(provider.connection, vaultPda, undefined, tokenProgram);
+
+          const accounts = {
+            projectConfig: projectConfigPda,
+            stakeInfo: activeStake.pda,
+            user: user.publicKey,
+            vaultAuthority: vaultAuthorityPda,
+          };
+          console.log("claimRewards accounts:", JSON.stringify(accounts, (key, value) => (value?.toBase58 ? value.toBase58() : value), 2));
+
+          await program.methods.claimRewards(activeStake.id)
+            .accountsStrict(accounts)
+            .remainingAccounts(rewardRemainingAccounts())
+            .signers([user])
+            .rpc();
+
+          const balancesAfter = await Promise.all(rewardStreams.map(stream =>
+            getAccount(provider.connection, stream.userTokenAccount, undefined, tokenProgram)
+          ));
+          const stakeInfoAccount = await program.account.userStakeInfo.fetch(activeStake.pda);
+          rewardStreams.forEach((_, index) => {
+            const claimed = balancesAfter[index].amount - balancesBefore[index].amount;
+            assert.isTrue(claimed > BigInt(0));
+          });
+          assert.isTrue(stakeInfoAccount.rewardsClaimed[0].gtn(0));
+          assert.isTrue(stakeInfoAccount.rewardsClaimed[1].gtn(0));
+
+          // The principal stays in the vault and the stake stays locked
+          const vaultAccountAfter = await getAccount(provider.connection, vaultPda, undefined, tokenProgram);
+          assert.equal(vaultAccountAfter.amount.toString(), vaultAccountBefore.amount.toString());
+          assert.isTrue(stakeInfoAccount.isStaked);
+        });
+
+        it("Redistributes emergency unstake fees to the remaining stakers", async () => {
+          await program.methods
+            .setFeeRedistribution(10000)
+            .accountsStrict({
+              platformConfig: platformConfigPda,
+              projectConfig: projectConfigPda,
+              authority: authority,
+            })
+            .rpc();
+
+          const amountToStake = new anchor.BN(20 * 10 ** 9);
+          const durationDays = 30;
+          const stakeId = new anchor.BN(7 + suiteIndex * 100);
+
+          const [stakeInfoPda] = await anchor.web3.PublicKey.findProgramAddress(
+            [Buffer.from("stake"), projectConfigPda.toBuffer(), user.publicKey.toBuffer(), stakeId.toBuffer('le', 8)],
+            program.programId
+          );
+          const [unstakeInfoPda] = await anchor.web3.PublicKey.findProgramAddress(
+            [Buffer.from("unstake"), stakeInfoPda.toBuffer()],
+            program.programId
+          );
+
+          await (program.methods.stake as any)(amountToStake, durationDays, stakeId, false)
+            .accounts({
+              projectConfig: projectConfigPda,
+              stakeInfo: stakeInfoPda,
+              unstakeInfo: unstakeInfoPda,
+              user: user.publicKey,
+              userTokenAccount: userTokenAccount,
+              vault: vaultPda,
+              systemProgram: anchor.web3.SystemProgram.programId,
+              tokenProgram: tokenProgram,
+            })
+            .signers([user])
+            .rpc();
+
+          const projectConfig = await program.account.projectConfig.fetch(projectConfigPda);
+          const stakedTokenStream = rewardStreams[1]; // Pays the staked token
+          const feeWalletAccountBefore = await getAccount(provider.connection, feeWalletTokenAccount, undefined, tokenProgram);
+          const streamVaultBefore = await getAccount(provider.connection, stakedTokenStream.vault, undefined, tokenProgram);
+
+          const emergencyUnstake = (penaltyRewardVault: anchor.web3.PublicKey | null) =>
+            program.methods.emergencyUnstake(stakeId)
+              .accounts({
+                platformConfig: platformConfigPda,
+                projectConfig: projectConfigPda,
+                stakeInfo: stakeInfoPda,
+                unstakeInfo: unstakeInfoPda,
+                user: user.publicKey,
+                userTokenAccount: userTokenAccount,
+                tokenMint: tokenMint,
+                vault: vaultPda,
+                vaultAuthority: vaultAuthorityPda,
+                feeWallet: feeWalletTokenAccount,
+                penaltyRewardVault: penaltyRewardVault,
+                tokenProgram: tokenProgram,
+                systemProgram: anchor.web3.SystemProgram.programId,
+              })
+              .signers([user])
+              .rpc();
+
+          // The penalty stream's vault cannot be left out to send the whole fee to the fee wallet
+          try {
+            await emergencyUnstake(null);
+            assert.fail("Emergency unstake should have failed without the penalty stream's vault.");
+          } catch (error) {
+            assert.include(error.toString(), "MissingRewardAccounts");
+          }
+          await emergencyUnstake(stakedTokenStream.vault);
+
+          const feeAmount = BigInt(amountToStake.toString()) * BigInt(projectConfig.emergencyUnstakeFeeBps) / BigInt(10000);
+
+          // The whole fee went into the staked token stream instead of the fee wallet
+          const feeWalletAccountAfter = await getAccount(provider.connection, feeWalletTokenAccount, undefined, tokenProgram);
+          assert.equal(feeWalletAccountAfter.amount.toString(), feeWalletAccountBefore.amount.toString());
+          // The stake's own rewards were forfeited, so nothing left the stream vault
+          const stakeInfoAccount = await program.account.userStakeInfo.fetch(stakeInfoPda);
+          assert.isTrue(stakeInfoAccount.rewardsClaimed.every(claimed => claimed.toString() === "0"));
+          const streamVaultAfter = await getAccount(provider.connection, stakedTokenStream.vault, undefined, tokenProgram);
+          assert.equal(streamVaultAfter.amount.toString(), (streamVaultBefore.amount + feeAmount).toString());
+
+          const projectConfigAfter = await program.account.projectConfig.fetch(projectConfigPda);
+          assert.isTrue(projectConfigAfter.rewardStreams[1].totalDistributed.gte(new anchor.BN(feeAmount.toString())));
+        });
+        });
+      });
+
+      describe("Partial unstake", () => {
+        it("Withdraws part of a matured stake", async () => {
+          const amountToStake = new anchor.BN(40 * 10 ** 9);
+          const amountToWithdraw = new anchor.BN(10 * 10 ** 9);
+          const stakeId = new anchor.BN(8 + suiteIndex * 100);
+          const { stakeInfoPda } = await stakeTokens(amountToStake, 0, stakeId);
+          const stakeInfoBefore = await program.account.userStakeInfo.fetch(stakeInfoPda);
+
+          const projectConfig = await program.account.projectConfig.fetch(projectConfigPda);
+          const userTokenAccountBefore = await getAccount(provider.connection, userTokenAccount, undefined, tokenProgram);
+          const feeWalletAccountBefore = await getAccount(provider.connection, feeWalletTokenAccount, undefined, tokenProgram);
+
+          const accounts = {
+            platformConfig: platformConfigPda,
+            projectConfig: projectConfigPda,
+            stakeInfo: stakeInfoPda,
+            userProjectInfo: findUserProjectPda(),
+            user: user.publicKey,
+            userTokenAccount: userTokenAccount,
+            tokenMint: tokenMint,
+            vault: vaultPda,
+            vaultAuthority: vaultAuthorityPda,
+            feeWallet: feeWalletTokenAccount,
+            tokenProgram: tokenProgram,
+          };
+          console.log("unstakePartial accounts:", JSON.stringify(accounts, (key, value) => (value?.toBase58 ? value.toBase58() : value), 2));
+
+          await program.methods.unstakePartial(stakeId, amountToWithdraw)
+            .accountsStrict(accounts)
+            .remainingAccounts(rewardRemainingAccounts())
+            .signers([user])
+            .rpc();
+
+          const stakeInfoAfter = await program.account.userStakeInfo.fetch(stakeInfoPda);
+          assert.isTrue(stakeInfoAfter.isStaked);
+          assert.equal(stakeInfoAfter.amount.toString(), amountToStake.sub(amountToWithdraw).toString());
+          assert.equal(stakeInfoAfter.weight.toString(), stakeInfoBefore.weight.muln(3).divn(4).toString());
+
+          // The fee only applies to the withdrawn part
+          const feeAmount = BigInt(amountToWithdraw.toString()) * BigInt(projectConfig.unstakeFeeBps) / BigInt(10000);
+          const feeWalletAccountAfter = await getAccount(provider.connection, feeWalletTokenAccount, undefined, tokenProgram);
+          assert.equal(feeWalletAccountAfter.amount.toString(), (feeWalletAccountBefore.amount + feeAmount).toString());
+
+          // The user also receives staked token rewards into the same account
+          const stakedTokenRewards = BigInt(stakeInfoAfter.rewardsClaimed[1].toString());
+          const userTokenAccountAfter = await getAccount(provider.connection, userTokenAccount, undefined, tokenProgram);
+          const expectedUserBalance = userTokenAccountBefore.amount + BigInt(amountToWithdraw.toString()) - feeAmount + stakedTokenRewards;
+          assert.equal(userTokenAccountAfter.amount.toString(), expectedUserBalance.toString());
+        });
+
+        it("Fails to withdraw the whole stake with a partial unstake", async () => {
+          const amountToStake = new anchor.BN(5 * 10 ** 9);
+          const stakeId = new anchor.BN(9 + suiteIndex * 100);
+          const { stakeInfoPda } = await stakeTokens(amountToStake, 0, stakeId);
+
+          try {
+            await program.methods.unstakePartial(stakeId, amountToStake)
+              .accountsStrict({
+                platformConfig: platformConfigPda,
+                projectConfig: projectConfigPda,
+                stakeInfo: stakeInfoPda,
+                userProjectInfo: findUserProjectPda(),
+                user: user.publicKey,
+                userTokenAccount: userTokenAccount,
+                tokenMint: tokenMint,
+                vault: vaultPda,
+                vaultAuthority: vaultAuthorityPda,
+                feeWallet: feeWalletTokenAccount,
+                tokenProgram: tokenProgram,
+              })
+              .remainingAccounts(rewardRemainingAccounts())
+              .signers([user])
+              .rpc();
+            assert.fail("Partial unstake of the whole amount should have failed.");
+          } catch (error) {
+            assert.include(error.toString(), "InvalidPartialAmount");
+          }
+        });
+      });
+      });
+
+      describe("Increase stake", () => {
+        const increaseStake = (stakeId: anchor.BN, stakeInfoPda: anchor.web3.PublicKey, amount: anchor.BN) =>
+          program.methods.increaseStake(stakeId, amount)
+            .accountsStrict({
+              platformConfig: platformConfigPda,
+              projectConfig: projectConfigPda,
+              stakeInfo: stakeInfoPda,
+              userProjectInfo: findUserProjectPda(),
+              user: user.publicKey,
+              userTokenAccount: userTokenAccount,
+              tokenMint: tokenMint,
+              vault: vaultPda,
+              tokenProgram: tokenProgram,
+            })
+            .signers([user])
+            .rpc();
+
+        it("Tops up a stake and restarts its lock-up", async () => {
+          const amountToStake = new anchor.BN(10 * 10 ** 9);
+          const amountToAdd = new anchor.BN(5 * 10 ** 9);
+          const stakeId = new anchor.BN(10 + suiteIndex * 100);
+          const { stakeInfoPda } = await stakeTokens(amountToStake, 30, stakeId);
+          const stakeInfoBefore = await program.account.userStakeInfo.fetch(stakeInfoPda);
+          const vaultBefore = await getAccount(provider.connection, vaultPda, undefined, tokenProgram);
+
+          await increaseStake(stakeId, stakeInfoPda, amountToAdd);
+
+          const stakeInfoAfter = await program.account.userStakeInfo.fetch(stakeInfoPda);
+          assert.equal(stakeInfoAfter.amount.toString(), amountToStake.add(amountToAdd).toString());
+          // 30 days carry a 1.5x multiplier
+          assert.equal(stakeInfoAfter.weight.toString(), stakeInfoBefore.weight.add(amountToAdd.muln(15).divn(10)).toString());
+          assert.isTrue(stakeInfoAfter.stakeTimestamp.gte(stakeInfoBefore.stakeTimestamp));
+          const vaultAfter = await getAccount(provider.connection, vaultPda, undefined, tokenProgram);
+          assert.equal(vaultAfter.amount.toString(), (vaultBefore.amount + BigInt(amountToAdd.toString())).toString());
+        });
+
+        it("Averages the unlock time in weighted-average mode", async () => {
+          await program.methods
+            .setLockRestartMode({ weightedAverage: {} })
+            .accountsStrict({
+              platformConfig: platformConfigPda,
+              projectConfig: projectConfigPda,
+              authority: authority,
+            })
+            .rpc();
+
+          // An equal top-up moves the unlock time about halfway towards a full duration from now
+          const amount = new anchor.BN(10 * 10 ** 9);
+          const stakeId = new anchor.BN(11 + suiteIndex * 100);
+          const { stakeInfoPda } = await stakeTokens(amount, 14, stakeId);
+          const stakeInfoBefore = await program.account.userStakeInfo.fetch(stakeInfoPda);
+
+          await increaseStake(stakeId, stakeInfoPda, amount);
+
+          const stakeInfoAfter = await program.account.userStakeInfo.fetch(stakeInfoPda);
+          const durationSeconds = 14 * 24 * 60 * 60;
+          const unlockBefore = stakeInfoBefore.stakeTimestamp.toNumber() + durationSeconds;
+          const unlockAfter = stakeInfoAfter.stakeTimestamp.toNumber() + durationSeconds;
+          assert.isAbove(unlockAfter, unlockBefore);
+          assert.isBelow(unlockAfter, unlockBefore + durationSeconds);
+
+          await program.methods
+            .setLockRestartMode({ reset: {} })
+            .accountsStrict({
+              platformConfig: platformConfigPda,
+              projectConfig: projectConfigPda,
+              authority: authority,
+            })
+            .rpc();
+        });
+      });
+      });
+
+      describe("Extend lock", () => {
+        const extendLock = (stakeId: anchor.BN, stakeInfoPda: anchor.web3.PublicKey, newDurationDays: number) =>
+          program.methods.extendLock(stakeId, newDurationDays)
+            .accountsStrict({
+              projectConfig: projectConfigPda,
+              stakeInfo: stakeInfoPda,
+              user: user.publicKey,
+            })
+            .signers([user])
+            .rpc();
+
+        it("Moves a stake to a longer duration", async () => {
+          const amountToStake = new anchor.BN(10 * 10 ** 9);
+          const stakeId = new anchor.BN(12 + suiteIndex * 100);
+          const { stakeInfoPda } = await stakeTokens(amountToStake, 14, stakeId);
+
+          await extendLock(stakeId, stakeInfoPda, 90);
+
+          const stakeInfo = await program.account.userStakeInfo.fetch(stakeInfoPda);
+          assert.equal(stakeInfo.durationDays, 90);
+          // 90 days carry a 2x multiplier
+          assert.equal(stakeInfo.weight.toString(), amountToStake.muln(2).toString());
+        });
+
+        it("Fails to move a stake to a shorter duration", async () => {
+          const stakeId = new anchor.BN(12 + suiteIndex * 100);
+          const { stakeInfoPda } = await findStakePdas(stakeId);
+
+          try {
+            await extendLock(stakeId, stakeInfoPda, 30);
+            assert.fail("Extending to a shorter duration should have failed.");
+          } catch (error) {
+            assert.include(error.toString(), "LockNotExtended");
+          }
+        });
+      });
+      });
+
+      describe("Auto-renew", () => {
+        it("Stakes with auto-renew and turns it off", async () => {
+          const amountToStake = new anchor.BN(10 * 10 ** 9);
+          const stakeId = new anchor.BN(13 + suiteIndex * 100);
+          const { stakeInfoPda } = await stakeTokens(amountToStake, 30, stakeId, true);
+
+          const stakeInfoBefore = await program.account.userStakeInfo.fetch(stakeInfoPda);
+          assert.isTrue(stakeInfoBefore.autoRenew);
+
+          await program.methods.setAutoRenew(stakeId, false)
+            .accountsStrict({
+              projectConfig: projectConfigPda,
+              stakeInfo: stakeInfoPda,
+              user: user.publicKey,
+            })
+            .signers([user])
+            .rpc();
+
+          // The current cycle is kept, so the stake still unlocks 30 days after it was made
+          const stakeInfoAfter = await program.account.userStakeInfo.fetch(stakeInfoPda);
+          assert.isFalse(stakeInfoAfter.autoRenew);
+          assert.equal(stakeInfoAfter.stakeTimestamp.toString(), stakeInfoBefore.stakeTimestamp.toString());
+        });
+      });
+      });
+
+      describe("Unbonding cooldown", () => {
+        const setCooldown = (cooldownSeconds: number) =>
+          program.methods.setCooldown(new anchor.BN(cooldownSeconds))
+            .accountsStrict({
+              platformConfig: platformConfigPda,
+              projectConfig: projectConfigPda,
+              authority: authority,
+            })
+            .rpc();
+
+        const requestUnstake = (stakeId: anchor.BN, pdas: { stakeInfoPda: anchor.web3.PublicKey, unstakeInfoPda: anchor.web3.PublicKey }) =>
+          program.methods.requestUnstake(stakeId)
+            .accountsStrict({
+              platformConfig: platformConfigPda,
+              projectConfig: projectConfigPda,
+              stakeInfo: pdas.stakeInfoPda,
+              userProjectInfo: findUserProjectPda(),
+              unstakeInfo: pdas.unstakeInfoPda,
+              user: user.publicKey,
+              vaultAuthority: vaultAuthorityPda,
+              systemProgram: anchor.web3.SystemProgram.programId,
+            })
+            .remainingAccounts(rewardRemainingAccounts())
+            .signers([user])
+            .rpc();
+
+        const withdraw = (stakeId: anchor.BN, pdas: { stakeInfoPda: anchor.web3.PublicKey, unstakeInfoPda: anchor.web3.PublicKey }) =>
+          program.methods.withdraw(stakeId)
+            .accountsStrict({
+              platformConfig: platformConfigPda,
+              projectConfig: projectConfigPda,
+              stakeInfo: pdas.stakeInfoPda,
+              unstakeInfo: pdas.unstakeInfoPda,
+              user: user.publicKey,
+              userTokenAccount: userTokenAccount,
+              tokenMint: tokenMint,
+              vault: vaultPda,
+              vaultAuthority: vaultAuthorityPda,
+              feeWallet: feeWalletTokenAccount,
+              tokenProgram: tokenProgram,
+            })
+            .signers([user])
+            .rpc();
+
+        it("Requires request_unstake and keeps tokens until the cooldown ends", async () => {
+          await setCooldown(3600);
+
+          const amountToStake = new anchor.BN(10 * 10 ** 9);
+          const stakeId = new anchor.BN(14 + suiteIndex * 100);
+          const pdas = await stakeTokens(amountToStake, 0, stakeId);
+
+          try {
+            await program.methods.unstake(stakeId)
+              .accountsStrict({
+                platformConfig: platformConfigPda,
+                projectConfig: projectConfigPda,
+                stakeInfo: pdas.stakeInfoPda,
+                userProjectInfo: findUserProjectPda(),
+                unstakeInfo: pdas.unstakeInfoPda,
+                user: user.publicKey,
+                userTokenAccount: userTokenAccount,
+                tokenMint: tokenMint,
+                vault: vaultPda,
+                vaultAuthority: vaultAuthorityPda,
+                feeWallet: feeWalletTokenAccount,
+                tokenProgram: tokenProgram,
+                systemProgram: anchor.web3.SystemProgram.programId,
+              })
+              .remainingAccounts(rewardRemainingAccounts())
+              .signers([user])
+              .rpc();
+            assert.fail("Unstake should have failed while the project has a cooldown.");
+          } catch (error) {
+            assert.include(error.toString(), "CooldownRequired");
+          }
+
+          await requestUnstake(stakeId, pdas);
+
+          const stakeInfo = await program.account.userStakeInfo.fetch(pdas.stakeInfoPda);
+          assert.isFalse(stakeInfo.isStaked);
+          const unstakeInfo = await program.account.unstakeInfo.fetch(pdas.unstakeInfoPda);
+          assert.deepEqual(unstakeInfo.status, { cooling: {} });
+          assert.equal(unstakeInfo.withdrawableTimestamp.toNumber(), unstakeInfo.unstakeTimestamp.toNumber() + 3600);
+
+          try {
+            await withdraw(stakeId, pdas);
+            assert.fail("Withdraw should have failed before the cooldown ended.");
+          } catch (error) {
+            assert.include(error.toString(), "CooldownNotEnded");
+          }
+        });
+
+        it("Withdraws once the cooldown has ended", async () => {
+          await setCooldown(0);
+
+          const amountToStake = new anchor.BN(10 * 10 ** 9);
+          const stakeId = new anchor.BN(15 + suiteIndex * 100);
+          const pdas = await stakeTokens(amountToStake, 0, stakeId);
+          await requestUnstake(stakeId, pdas);
+
+          const userTokenAccountBefore = await getAccount(provider.connection, userTokenAccount, undefined, tokenProgram);
+          await withdraw(stakeId, pdas);
+
+          const unstakeInfo = await program.account.unstakeInfo.fetch(pdas.unstakeInfoPda);
+          assert.deepEqual(unstakeInfo.status, { unstaked: {} });
+
+          const projectConfig = await program.account.projectConfig.fetch(projectConfigPda);
+          const feeAmount = BigInt(amountToStake.toString()) * BigInt(projectConfig.unstakeFeeBps) / BigInt(10000);
+          const userTokenAccountAfter = await getAccount(provider.connection, userTokenAccount, undefined, tokenProgram);
+          const expectedUserBalance = userTokenAccountBefore.amount + BigInt(amountToStake.toString()) - feeAmount;
+          assert.equal(userTokenAccountAfter.amount.toString(), expectedUserBalance.toString());
+        });
+
+        it("Keeps existing stakes on their cooldown when it is raised", async () => {
+          const stakeId = new anchor.BN(25 + suiteIndex * 100);
+          const pdas = await stakeTokens(new anchor.BN(10 * 10 ** 9), 0, stakeId);
+          await setCooldown(3600);
+
+          await requestUnstake(stakeId, pdas);
+          const unstakeInfo = await program.account.unstakeInfo.fetch(pdas.unstakeInfoPda);
+          assert.equal(unstakeInfo.withdrawableTimestamp.toNumber(), unstakeInfo.unstakeTimestamp.toNumber());
+          await withdraw(stakeId, pdas);
+
+          await setCooldown(0);
+        });
+
+        it("Rejects a cooldown above the maximum", async () => {
+          try {
+            await setCooldown(30 * 24 * 60 * 60 + 1);
+            assert.fail("Setting a cooldown above MAX_COOLDOWN_SECONDS should have failed.");
+          } catch (error) {
+            assert.include(error.toString(), "InvalidCooldown");
+          }
+        });
+      });
+      });
+
+      describe("Close position", () => {
+        const closePosition = (stakeId: anchor.BN, pdas: { stakeInfoPda: anchor.web3.PublicKey, unstakeInfoPda: anchor.web3.PublicKey }) =>
+          program.methods.closePosition(stakeId)
+            .accountsStrict({
+              projectConfig: projectConfigPda,
+              stakeInfo: pdas.stakeInfoPda,
+              unstakeInfo: pdas.unstakeInfoPda,
+              user: user.publicKey,
+            })
+            .signers([user])
+            .rpc();
+
+        it("Closes a withdrawn stake and refunds its rent", async () => {
+          // Withdrawn in the cooldown tests
+          const stakeId = new anchor.BN(15 + suiteIndex * 100);
+          const pdas = await findStakePdas(stakeId);
+          const userBalanceBefore = await provider.connection.getBalance(user.publicKey);
+
+          await closePosition(stakeId, pdas);
+
+          assert.isNull(await provider.connection.getAccountInfo(pdas.stakeInfoPda));
+          assert.isNull(await provider.connection.getAccountInfo(pdas.unstakeInfoPda));
+          const userBalanceAfter = await provider.connection.getBalance(user.publicKey);
+          assert.isAbove(userBalanceAfter, userBalanceBefore);
+        });
+
+        it("Fails to close an active stake", async () => {
+          const stakeId = new anchor.BN(13 + suiteIndex * 100);
+          try {
+            await closePosition(stakeId, await findStakePdas(stakeId));
+            assert.fail("Closing an active stake should have failed.");
+          } catch (error) {
+            assert.include(error.toString(), "StakeStillActive");
+          }
+        });
+      });
+      });
+
+      describe("Pause", () => {
+        const setPaused = (projectConfig: anchor.web3.PublicKey | null, paused: boolean, unstakePaused: boolean) =>
+          program.methods.setPaused(paused, unstakePaused)
+            .accountsStrict({
+              platformConfig: platformConfigPda,
+              projectConfig: projectConfig,
+              authority: authority,
+            })
+            .rpc();
+
+        it("Blocks staking while a project is paused", async () => {
+          await setPaused(projectConfigPda, true, true);
+          const projectConfig = await program.account.projectConfig.fetch(projectConfigPda);
+          assert.isTrue(projectConfig.paused);
+          assert.isTrue(projectConfig.unstakePaused);
+
+          try {
+            await stakeTokens(new anchor.BN(10 * 10 ** 9), 30, new anchor.BN(16 + suiteIndex * 100));
+            assert.fail("Staking should have failed while the project is paused.");
+          } catch (error) {
+            assert.include(error.toString(), "StakingPaused");
+          }
+
+          await setPaused(projectConfigPda, false, false);
+        });
+
+        it("Blocks staking while the platform is paused", async () => {
+          await setPaused(null, true, false);
+          const platformConfig = await program.account.platformConfig.fetch(platformConfigPda);
+          assert.isTrue(platformConfig.paused);
+          assert.isFalse(platformConfig.unstakePaused);
+
+          try {
+            await stakeTokens(new anchor.BN(10 * 10 ** 9), 30, new anchor.BN(16 + suiteIndex * 100));
+            assert.fail("Staking should have failed while the platform is paused.");
+          } catch (error) {
+            assert.include(error.toString(), "StakingPaused");
+          }
+
+          await setPaused(null, false, false);
+        });
+
+        it("Lets matured stakes leave through emergency unstake while unstaking is paused", async () => {
+          const amountToStake = new anchor.BN(10 * 10 ** 9);
+          const stakeId = new anchor.BN(26 + suiteIndex * 100);
+          const { stakeInfoPda, unstakeInfoPda } = await stakeTokens(amountToStake, 0, stakeId);
+          await setPaused(projectConfigPda, false, true);
+
+          const stakeInfo = await program.account.userStakeInfo.fetch(stakeInfoPda);
+          const userTokenAccountBefore = await getAccount(provider.connection, userTokenAccount, undefined, tokenProgram);
+          await program.methods.emergencyUnstake(stakeId)
+            .accountsStrict({
+              platformConfig: platformConfigPda,
+              projectConfig: projectConfigPda,
+              stakeInfo: stakeInfoPda,
+              userProjectInfo: findUserProjectPda(),
+              unstakeInfo: unstakeInfoPda,
+              user: user.publicKey,
+              userTokenAccount: userTokenAccount,
+              tokenMint: tokenMint,
+              vault: vaultPda,
+              vaultAuthority: vaultAuthorityPda,
+              feeWallet: feeWalletTokenAccount,
+              penaltyRewardVault: rewardStreams[1].vault,
+              tokenProgram: tokenProgram,
+              systemProgram: anchor.web3.SystemProgram.programId,
+            })
+            .signers([user])
+            .rpc();
+
+          // The stake pays its unstake fee, not the emergency fee
+          const feeAmount = BigInt(amountToStake.toString()) * BigInt(stakeInfo.unstakeFeeBps) / BigInt(10000);
+          const userTokenAccountAfter = await getAccount(provider.connection, userTokenAccount, undefined, tokenProgram);
+          assert.equal((userTokenAccountAfter.amount - userTokenAccountBefore.amount).toString(), (BigInt(amountToStake.toString()) - feeAmount).toString());
+          const unstakeInfo = await program.account.unstakeInfo.fetch(unstakeInfoPda);
+          assert.deepEqual(unstakeInfo.status, { emergencyUnstaked: {} });
+
+          await setPaused(projectConfigPda, false, false);
+        });
+      });
+      });
+
+      describe("Proposals", () => {
+        const findProposalPda = (proposalId: anchor.BN) =>
+          anchor.web3.PublicKey.findProgramAddressSync(
+            [Buffer.from("proposal"), proposalId.toBuffer('le', 8)],
+            program.programId
+          )[0];
+
+        // Creates a proposal signed by the main authority and returns its ID
+        const propose = async (action: any) => {
+          const platformConfig = await program.account.platformConfig.fetch(platformConfigPda);
+          const proposalId = platformConfig.proposalCount;
+          await program.methods.propose(action)
+            .accountsStrict({
+              platformConfig: platformConfigPda,
+              proposal: findProposalPda(proposalId),
+              proposer: authority,
+              systemProgram: anchor.web3.SystemProgram.programId,
+            })
+            .rpc();
+          return proposalId;
+        };
+
+        const execute = (proposalId: anchor.BN, projectConfig: anchor.web3.PublicKey | null = null) =>
+          program.methods.execute(proposalId)
+            .accountsStrict({
+              platformConfig: platformConfigPda,
+              proposal: findProposalPda(proposalId),
+              projectConfig: projectConfig,
+              executor: authority,
+              systemProgram: anchor.web3.SystemProgram.programId,
+            })
+            .rpc();
+
+        it("Executes proposals right away with a threshold of 1", async () => {
+          const nominateId = await propose({ nominateAuthority: { authority: newAuthority.publicKey, roles: ROLE_SUPER_ADMIN | ROLE_FEE_MANAGER } });
+          await execute(nominateId);
+          await program.methods
+            .acceptAuthority()
+            .accountsStrict({
+              platformConfig: platformConfigPda,
+              nominee: newAuthority.publicKey,
+              systemProgram: anchor.web3.SystemProgram.programId,
+            })
+            .signers([newAuthority])
+            .rpc();
+          const thresholdId = await propose({ setApprovalThreshold: { threshold: 2 } });
+          await execute(thresholdId);
+
+          const platformConfig = await program.account.platformConfig.fetch(platformConfigPda);
+          assert.equal(platformConfig.approvalThreshold, 2);
+          assert.ok(platformConfig.authorities.some(auth => auth.authority.equals(newAuthority.publicKey)));
+        });
+
+        it("Rejects direct fee changes while the threshold is above 1", async () => {
+          try {
+            await program.methods
+              .updateProjectConfig(feeWallet.publicKey, 100, 100)
+              .accountsStrict({
+                platformConfig: platformConfigPda,
+                projectConfig: projectConfigPda,
+                authority: authority,
+              })
+              .rpc();
+            assert.fail("A direct fee change should have failed.");
+          } catch (error) {
+            assert.include(error.toString(), "ProposalRequired");
+          }
+        });
+
+        it("Rejects project authority transfers by a single super admin while the threshold is above 1", async () => {
+          try {
+            await program.methods
+              .transferProjectAuthority(newAuthority.publicKey)
+              .accountsStrict({
+                platformConfig: platformConfigPda,
+                projectConfig: projectConfigPda,
+                authority: newAuthority.publicKey,
+              })
+              .signers([newAuthority])
+              .rpc();
+            assert.fail("A super admin should not transfer a project on their own.");
+          } catch (error) {
+            assert.include(error.toString(), "ProposalRequired");
+          }
+        });
+
+        it("Changes the fee mode through a proposal while the threshold is above 1", async () => {
+          const feeMode = { current: {} };
+          try {
+            await program.methods
+              .setFeeMode(feeMode)
+              .accountsStrict({
+                platformConfig: platformConfigPda,
+                projectConfig: newAuthorityProjectConfigPda,
+                authority: authority,
+              })
+              .rpc();
+            assert.fail("A direct fee mode change should have failed.");
+          } catch (error) {
+            assert.include(error.toString(), "ProposalRequired");
+          }
+
+          const proposalId = await propose({ setFeeMode: { projectConfig: newAuthorityProjectConfigPda, feeMode } });
+          await program.methods.approve(proposalId)
+            .accountsStrict({
+              platformConfig: platformConfigPda,
+              proposal: findProposalPda(proposalId),
+              approver: newAuthority.publicKey,
+            })
+            .signers([newAuthority])
+            .rpc();
+          await execute(proposalId, newAuthorityProjectConfigPda);
+
+          const projectConfig = await program.account.projectConfig.fetch(newAuthorityProjectConfigPda);
+          assert.deepEqual(projectConfig.feeMode, feeMode);
+        });
+
+        it("Refuses to leave fewer super admins than the threshold", async () => {
+          const proposalId = await propose({ removeAuthority: { authority: newAuthority.publicKey } });
+          await program.methods.approve(proposalId)
+            .accountsStrict({
+              platformConfig: platformConfigPda,
+              proposal: findProposalPda(proposalId),
+              approver: newAuthority.publicKey,
+            })
+            .signers([newAuthority])
+            .rpc();
+          try {
+            await execute(proposalId);
+            assert.fail("Removing a super admin below the threshold should have failed.");
+          } catch (error) {
+            assert.include(error.toString(), "ApproversBelowThreshold");
+          }
+        });
+
+        it("Refuses to leave fewer fee managers than the threshold", async () => {
+          const proposalId = await propose({ setAuthorityRoles: { authority: newAuthority.publicKey, roles: ROLE_SUPER_ADMIN } });
+          await program.methods.approve(proposalId)
+            .accountsStrict({
+              platformConfig: platformConfigPda,
+              proposal: findProposalPda(proposalId),
+              approver: newAuthority.publicKey,
+            })
+            .signers([newAuthority])
+            .rpc();
+          try {
+            await execute(proposalId);
+            assert.fail("Taking the fee manager role below the threshold should have failed.");
+          } catch (error) {
+            assert.include(error.toString(), "ApproversBelowThreshold");
+          }
+        });
+
+        it("Needs a second approval before executing", async () => {
+          const proposalId = await propose({ setApprovalThreshold: { threshold: 1 } });
+          try {
+            await execute(proposalId);
+            assert.fail("Execution should have failed with a single approval.");
+          } catch (error) {
+            assert.include(error.toString(), "ThresholdNotReached");
+          }
+
+          await program.methods.approve(proposalId)
+            .accountsStrict({
+              platformConfig: platformConfigPda,
+              proposal: findProposalPda(proposalId),
+              approver: newAuthority.publicKey,
+            })
+            .signers([newAuthority])
+            .rpc();
+          await execute(proposalId);
+
+          const proposal = await program.account.proposal.fetch(findProposalPda(proposalId));
+          assert.isTrue(proposal.executed);
+          assert.equal(proposal.approvals.length, 2);
+          const platformConfig = await program.account.platformConfig.fetch(platformConfigPda);
+          assert.equal(platformConfig.approvalThreshold, 1);
+
+          // Restore the single-authority setup for the next suite
+          await program.methods
+            .removeAuthority(newAuthority.publicKey)
+            .accountsStrict({
+              platformConfig: platformConfigPda,
+              authority: authority,
+              systemProgram: anchor.web3.SystemProgram.programId,
+            })
+            .rpc();
+        });
+      });
+      });
+
+      describe("Timelocked config", () => {
+        it("Queues fee changes while the project has stakes", async () => {
+          const projectConfigBefore = await program.account.projectConfig.fetch(projectConfigPda);
+
+          await program.methods
+            .updateProjectConfig(feeWallet.publicKey, 200, 5000)
+            .accountsStrict({
+              platformConfig: platformConfigPda,
+              projectConfig: projectConfigPda,
+              authority: authority,
+            })
+            .rpc();
+
+          const projectConfig = await program.account.projectConfig.fetch(projectConfigPda);
+          assert.equal(projectConfig.unstakeFeeBps, projectConfigBefore.unstakeFeeBps);
+          assert.equal(projectConfig.emergencyUnstakeFeeBps, projectConfigBefore.emergencyUnstakeFeeBps);
+          assert.equal(projectConfig.pendingConfig.unstakeFeeBps, 200);
+          assert.equal(projectConfig.pendingConfig.emergencyUnstakeFeeBps, 5000);
+          const twoDays = 2 * 24 * 60 * 60;
+          assert.isAtLeast(projectConfig.pendingConfig.effectiveAt.toNumber(), Math.floor(Date.now() / 1000) + twoDays - 60);
+
+          try {
+            await program.methods
+              .applyPendingConfig()
+              .accountsStrict({ projectConfig: projectConfigPda })
+              .rpc();
+            assert.fail("Applying the change before the timelock should have failed.");
+          } catch (error) {
+            assert.include(error.toString(), "ConfigChangeNotReady");
+          }
+        });
+
+        it("Queues fee changes while only cooling stakes remain", async () => {
+          // A separate project whose only stake is waiting out its cooldown
+          const { projectCount } = await program.account.platformConfig.fetch(platformConfigPda);
+          [coolingProjectConfigPda] = anchor.web3.PublicKey.findProgramAddressSync(
+            [Buffer.from("project"), projectCount.toBuffer('le', 8)],
+            program.programId
+          );
+          const [coolingVaultPda] = anchor.web3.PublicKey.findProgramAddressSync(
+            [Buffer.from("vault"), projectCount.toBuffer('le', 8)],
+            program.programId
+          );
+          const [coolingVaultAuthorityPda] = anchor.web3.PublicKey.findProgramAddressSync(
+            [Buffer.from("vault-authority"), projectCount.toBuffer('le', 8)],
+            program.programId
+          );
+          await program.methods
+            .registerProject("Cooling Project", [{ durationDays: 0, multiplierBps: 10000 }])
+            .accountsStrict({
+              platformConfig: platformConfigPda,
+              projectConfig: coolingProjectConfigPda,
+              tokenMint: tokenMint,
+              vault: coolingVaultPda,
+              vaultAuthority: coolingVaultAuthorityPda,
+              authority: authority,
+              systemProgram: anchor.web3.SystemProgram.programId,
+              tokenProgram: tokenProgram,
+              rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+            })
+            .rpc();
+          await program.methods.setCooldown(new anchor.BN(3600))
+            .accountsStrict({
+              platformConfig: platformConfigPda,
+              projectConfig: coolingProjectConfigPda,
+              authority: authority,
+            })
+            .rpc();
+
+          const stakeId = new anchor.BN(24 + suiteIndex * 100);
+          const [stakeInfoPda] = anchor.web3.PublicKey.findProgramAddressSync(
+            [Buffer.from("stake"), coolingProjectConfigPda.toBuffer(), user.publicKey.toBuffer(), stakeId.toBuffer('le', 8)],
+            program.programId
+          );
+          const [unstakeInfoPda] = anchor.web3.PublicKey.findProgramAddressSync(
+            [Buffer.from("unstake"), stakeInfoPda.toBuffer()],
+            program.programId
+          );
+          await (program.methods.stake as any)(new anchor.BN(10 * 10 ** 9), 0, stakeId, false)
+            .accounts({
+              projectConfig: coolingProjectConfigPda,
+              stakeInfo: stakeInfoPda,
+              unstakeInfo: unstakeInfoPda,
+              user: user.publicKey,
+              userTokenAccount: userTokenAccount,
+              vault: coolingVaultPda,
+              systemProgram: anchor.web3.SystemProgram.programId,
+              tokenProgram: tokenProgram,
+            })
+            .signers([user])
+            .rpc();
+          const [userProjectPda] = anchor.web3.PublicKey.findProgramAddressSync(
+            [Buffer.from("user-project"), coolingProjectConfigPda.toBuffer(), user.publicKey.toBuffer()],
+            program.programId
+          );
+          await program.methods.requestUnstake(stakeId)
+            .accountsStrict({
+              platformConfig: platformConfigPda,
+              projectConfig: coolingProjectConfigPda,
+              stakeInfo: stakeInfoPda,
+              userProjectInfo: userProjectPda,
+              unstakeInfo: unstakeInfoPda,
+              user: user.publicKey,
+              vaultAuthority: coolingVaultAuthorityPda,
+              systemProgram: anchor.web3.SystemProgram.programId,
+            })
+            .signers([user])
+            .rpc();
+
+          // No weight is left, but the cooling tokens still leave with the current fees
+          await program.methods
+            .updateProjectConfig(feeWallet.publicKey, 200, 5000)
+            .accountsStrict({
+              platformConfig: platformConfigPda,
+              projectConfig: coolingProjectConfigPda,
+              authority: authority,
+            })
+            .rpc();
+
+          const projectConfig = await program.account.projectConfig.fetch(coolingProjectConfigPda);
+          assert.equal(projectConfig.totalWeight.toString(), "0");
+          assert.equal(projectConfig.unstakeFeeBps, 0);
+          assert.equal(projectConfig.pendingConfig.unstakeFeeBps, 200);
+        });
+      });
+
+      describe("Fee snapshot", () => {
+        it("Records the project's fees on new stakes", async () => {
+          const amountToStake = new anchor.BN(10 * 10 ** 9);
+          const stakeId = new anchor.BN(17 + suiteIndex * 100);
+          const { stakeInfoPda } = await stakeTokens(amountToStake, 30, stakeId);
+
+          const projectConfig = await program.account.projectConfig.fetch(projectConfigPda);
+          const stakeInfo = await program.account.userStakeInfo.fetch(stakeInfoPda);
+          assert.deepEqual(projectConfig.feeMode, { snapshot: {} });
+          assert.equal(stakeInfo.unstakeFeeBps, projectConfig.unstakeFeeBps);
+          assert.equal(stakeInfo.emergencyUnstakeFeeBps, projectConfig.emergencyUnstakeFeeBps);
+          assert.deepEqual(stakeInfo.feeMode, projectConfig.feeMode);
+        });
+
+        it("Switches a project to current fees and back without changing existing stakes", async () => {
+          const { stakeInfoPda } = await findStakePdas(new anchor.BN(17 + suiteIndex * 100));
+          for (const feeMode of [{ current: {} }, { snapshot: {} }]) {
+            await program.methods
+              .setFeeMode(feeMode)
+              .accountsStrict({
+                platformConfig: platformConfigPda,
+                projectConfig: projectConfigPda,
+                authority: authority,
+              })
+              .rpc();
+
+            const projectConfig = await program.account.projectConfig.fetch(projectConfigPda);
+            assert.deepEqual(projectConfig.feeMode, feeMode);
+            const stakeInfo = await program.account.userStakeInfo.fetch(stakeInfoPda);
+            assert.deepEqual(stakeInfo.feeMode, { snapshot: {} });
+          }
+        });
+      });
+
+      describe("Project authority transfer", () => {
+        it("Hands a project over once the new authority accepts", async () => {
+          await program.methods
+            .transferProjectAuthority(user.publicKey)
+            .accountsStrict({
+              platformConfig: platformConfigPda,
+              projectConfig: projectConfigPda,
+              authority: authority,
+            })
+            .rpc();
+
+          const projectConfigPending = await program.account.projectConfig.fetch(projectConfigPda);
+          assert.isTrue(projectConfigPending.pendingAuthority.equals(user.publicKey));
+          assert.isAbove(projectConfigPending.pendingAuthorityExpiresAt.toNumber(), Math.floor(Date.now() / 1000));
+
+          try {
+            await program.methods
+              .acceptProjectAuthority()
+              .accountsStrict({ projectConfig: projectConfigPda, newAuthority: newAuthority.publicKey })
+              .signers([newAuthority])
+              .rpc();
+            assert.fail("Only the pending authority should be able to accept.");
+          } catch (error) {
+            assert.include(error.toString(), "NotPendingProjectAuthority");
+          }
+
+          await program.methods
+            .acceptProjectAuthority()
+            .accountsStrict({ projectConfig: projectConfigPda, newAuthority: user.publicKey })
+            .signers([user])
+            .rpc();
+
+          let projectConfig = await program.account.projectConfig.fetch(projectConfigPda);
+          assert.isTrue(projectConfig.authority.equals(user.publicKey));
+          assert.isNull(projectConfig.pendingAuthority);
+
+          // The new owner hands the project back
+          await program.methods
+            .transferProjectAuthority(authority)
+            .accountsStrict({
+              platformConfig: platformConfigPda,
+              projectConfig: projectConfigPda,
+              authority: user.publicKey,
+            })
+            .signers([user])
+            .rpc();
+          await program.methods
+            .acceptProjectAuthority()
+            .accountsStrict({ projectConfig: projectConfigPda, newAuthority: authority })
+            .rpc();
+
+          projectConfig = await program.account.projectConfig.fetch(projectConfigPda);
+          assert.isTrue(projectConfig.authority.equals(authority));
+        });
+      });
+
+      describe("Stake limits", () => {
+        const setStakeLimits = (min: anchor.BN, max: anchor.BN, maxPerUser: anchor.BN) =>
+          program.methods
+            .setStakeLimits(min, max, maxPerUser)
+            .accountsStrict({
+              platformConfig: platformConfigPda,
+              projectConfig: projectConfigPda,
+              authority: authority,
+            })
+            .rpc();
+
+        const expectStakeError = async (amount: anchor.BN, stakeId: anchor.BN, errorCode: string) => {
+          try {
+            await stakeTokens(amount, 30, stakeId);
+            assert.fail(`Staking should have failed with ${errorCode}.`);
+          } catch (error) {
+            assert.include(error.toString(), errorCode);
+          }
+        };
+
+        it("Enforces the minimum, maximum and per-user limits", async () => {
+          const token = new anchor.BN(10 ** 9);
+          const totalBefore = (await program.account.userProjectInfo.fetch(findUserProjectPda())).totalStaked;
+          await setStakeLimits(token, token.muln(50), totalBefore.add(token.muln(15)));
+
+          const stakeId = new anchor.BN(18 + suiteIndex * 100);
+          await expectStakeError(token.divn(2), stakeId, "StakeBelowMinimum");
+          await expectStakeError(token.muln(60), stakeId, "StakeAboveMaximum");
+
+          await stakeTokens(token.muln(10), 30, stakeId);
+          const userProjectInfo = await program.account.userProjectInfo.fetch(findUserProjectPda());
+          assert.equal(userProjectInfo.totalStaked.toString(), totalBefore.add(token.muln(10)).toString());
+
+          // A second stake of 10 would take the user past the cap
+          await expectStakeError(token.muln(10), new anchor.BN(19 + suiteIndex * 100), "UserStakeCapExceeded");
+
+          await setStakeLimits(new anchor.BN(0), new anchor.BN(0), new anchor.BN(0));
+        });
+      });
+
+      describe("Total stake cap", () => {
+        const setMaxTotalStaked = (maxTotalStaked: anchor.BN) =>
+          program.methods
+            .setMaxTotalStaked(maxTotalStaked)
+            .accountsStrict({
+              platformConfig: platformConfigPda,
+              projectConfig: projectConfigPda,
+              authority: authority,
+            })
+            .rpc();
+
+        it("Tracks the staked total and enforces the cap", async () => {
+          const amount = new anchor.BN(10 * 10 ** 9);
+          const projectConfigBefore = await program.account.projectConfig.fetch(projectConfigPda);
+
+          await stakeTokens(amount, 30, new anchor.BN(20 + suiteIndex * 100));
+          const projectConfig = await program.account.projectConfig.fetch(projectConfigPda);
+          assert.equal(projectConfig.totalStaked.toString(), projectConfigBefore.totalStaked.add(amount).toString());
+          assert.equal(projectConfig.activeStakeCount.toNumber(), projectConfigBefore.activeStakeCount.toNumber() + 1);
+
+          await setMaxTotalStaked(projectConfig.totalStaked.add(amount.divn(2)));
+          try {
+            await stakeTokens(amount, 30, new anchor.BN(21 + suiteIndex * 100));
+            assert.fail("Staking past the cap should have failed.");
+          } catch (error) {
+            assert.include(error.toString(), "TotalStakeCapExceeded");
+          }
+
+          await setMaxTotalStaked(new anchor.BN(0));
+        });
+      });
+
+      describe("Staking window", () => {
+        const setStakeWindow = (start: anchor.BN | null, end: anchor.BN | null) =>
+          program.methods
+            .setStakeWindow(start, end)
+            .accountsStrict({
+              platformConfig: platformConfigPda,
+              projectConfig: projectConfigPda,
+              authority: authority,
+            })
+            .rpc();
+
+        it("Rejects stakes outside the window", async () => {
+          const now = Math.floor(Date.now() / 1000);
+          const amount = new anchor.BN(10 * 10 ** 9);
+          const stakeId = new anchor.BN(22 + suiteIndex * 100);
+          const windows: [anchor.BN, anchor.BN, string][] = [
+            [new anchor.BN(now + 3600), new anchor.BN(now + 7200), "StakingNotStarted"],
+            [new anchor.BN(now - 7200), new anchor.BN(now - 3600), "StakingEnded"],
+          ];
+          for (const [start, end, errorCode] of windows) {
+            await setStakeWindow(start, end);
+            try {
+              await stakeTokens(amount, 30, stakeId);
+              assert.fail(`Staking should have failed with ${errorCode}.`);
+            } catch (error) {
+              assert.include(error.toString(), errorCode);
+            }
+          }
+
+          await setStakeWindow(null, null);
+          const projectConfig = await program.account.projectConfig.fetch(projectConfigPda);
+          assert.isNull(projectConfig.stakeStartTs);
+          assert.isNull(projectConfig.stakeEndTs);
+        });
+      });
+
+      describe("Project lifecycle", () => {
+        const setProjectStatus = (status: any, sunsetFeeWaived: boolean) =>
+          program.methods
+            .setProjectStatus(status, sunsetFeeWaived)
+            .accountsStrict({
+              platformConfig: platformConfigPda,
+              projectConfig: projectConfigPda,
+              authority: authority,
+            })
+            .rpc();
+
+        it("Rejects stakes once deposits are closed", async () => {
+          await setProjectStatus({ depositsClosed: {} }, false);
+          try {
+            await stakeTokens(new anchor.BN(10 * 10 ** 9), 30, new anchor.BN(23 + suiteIndex * 100));
+            assert.fail("Staking should have failed with DepositsClosed.");
+          } catch (error) {
+            assert.include(error.toString(), "DepositsClosed");
+          }
+          await setProjectStatus({ active: {} }, false);
+        });
+
+        it("Lets a locked stake leave without a fee after sunset", async () => {
+          const amount = new anchor.BN(10 * 10 ** 9);
+          const stakeId = new anchor.BN(23 + suiteIndex * 100);
+          const { stakeInfoPda, unstakeInfoPda } = await stakeTokens(amount, 30, stakeId);
+
+          await setProjectStatus({ sunset: {} }, true);
+          const userTokenAccountBefore = await getAccount(provider.connection, userTokenAccount, undefined, tokenProgram);
+          await program.methods.unstake(stakeId)
+            .accounts({
+              projectConfig: projectConfigPda,
+              stakeInfo: stakeInfoPda,
+              unstakeInfo: unstakeInfoPda,
+              user: user.publicKey,
+              userTokenAccount: userTokenAccount,
+              tokenMint: tokenMint,
+              vault: vaultPda,
+              vaultAuthority: vaultAuthorityPda,
+              feeWallet: feeWalletTokenAccount,
+              tokenProgram: tokenProgram,
+              systemProgram: anchor.web3.SystemProgram.programId,
+            })
+            .remainingAccounts(rewardRemainingAccounts())
+            .signers([user])
+            .rpc();
+
+          // The 30-day lock is over and no fee was taken
+          const userTokenAccountAfter = await getAccount(provider.connection, userTokenAccount, undefined, tokenProgram);
+          assert.isTrue(userTokenAccountAfter.amount - userTokenAccountBefore.amount >= BigInt(amount.toString()));
+
+          try {
+            await setProjectStatus({ active: {} }, false);
+            assert.fail("A sunset project should not become active again.");
+          } catch (error) {
+            assert.include(error.toString(), "ProjectSunset");
+          }
+        });
+
+        it("Keeps emergency unstake open after sunset while unstaking is paused", async () => {
+          await program.methods.setPaused(false, true)
+            .accountsStrict({
+              platformConfig: platformConfigPda,
+              projectConfig: projectConfigPda,
+              authority: authority,
+            })
+            .rpc();
+
+          // Stake 17 is still locked for 30 days and recorded a non-zero emergency fee
+          const stakeId = new anchor.BN(17 + suiteIndex * 100);
+          const { stakeInfoPda, unstakeInfoPda } = await findStakePdas(stakeId);
+          const { amount } = await program.account.userStakeInfo.fetch(stakeInfoPda);
+          const userTokenAccountBefore = await getAccount(provider.connection, userTokenAccount, undefined, tokenProgram);
+          await program.methods.emergencyUnstake(stakeId)
+            .accounts({
+              platformConfig: platformConfigPda,
+              projectConfig: projectConfigPda,
+              stakeInfo: stakeInfoPda,
+              unstakeInfo: unstakeInfoPda,
+              user: user.publicKey,
+              userTokenAccount: userTokenAccount,
+              tokenMint: tokenMint,
+              vault: vaultPda,
+              vaultAuthority: vaultAuthorityPda,
+              feeWallet: feeWalletTokenAccount,
+              penaltyRewardVault: rewardStreams[1].vault,
+              tokenProgram: tokenProgram,
+              systemProgram: anchor.web3.SystemProgram.programId,
+            })
+            .signers([user])
+            .rpc();
+
+          // The unstake fee is waived, so the whole amount came back
+          const userTokenAccountAfter = await getAccount(provider.connection, userTokenAccount, undefined, tokenProgram);
+          assert.equal((userTokenAccountAfter.amount - userTokenAccountBefore.amount).toString(), amount.toString());
+
+          await program.methods.setPaused(false, false)
+            .accountsStrict({
+              platformConfig: platformConfigPda,
+              projectConfig: projectConfigPda,
+              authority: authority,
+            })
+            .rpc();
+        });
+      });
+
+      describe("Close project", () => {
+        const closeProject = async (
+          projectConfig: anchor.web3.PublicKey,
+          receiver: anchor.web3.PublicKey,
+          remainingAccounts: anchor.web3.AccountMeta[] = []
+        ) => {
+          const project = await program.account.projectConfig.fetch(projectConfig);
+          const [vaultAuthority] = anchor.web3.PublicKey.findProgramAddressSync(
+            [Buffer.from("vault-authority"), project.projectId.toBuffer('le', 8)],
+            program.programId
+          );
+          await program.methods
+            .closeProject()
+            .accountsStrict({
+              platformConfig: platformConfigPda,
+              projectConfig: projectConfig,
+              vault: project.vault,
+              vaultAuthority: vaultAuthority,
+              receiver: receiver,
+              authority: authority,
+              tokenProgram: tokenProgram,
+            })
+            .remainingAccounts(remainingAccounts)
+            .rpc();
+        };
+
+        it("Fails to close a project with staked tokens", async () => {
+          try {
+            await closeProject(projectConfigPda, authority);
+            assert.fail("Closing a project with stakes should have failed.");
+          } catch (error) {
+            assert.include(error.toString(), "ProjectNotEmpty");
+          }
+        });
+
+        it("Closes an empty project and its vault", async () => {
+          const { vault } = await program.account.projectConfig.fetch(newAuthorityProjectConfigPda);
+          const receiverBalanceBefore = await provider.connection.getBalance(newAuthority.publicKey);
+
+          await closeProject(newAuthorityProjectConfigPda, newAuthority.publicKey);
+
+          assert.isNull(await provider.connection.getAccountInfo(newAuthorityProjectConfigPda));
+          assert.isNull(await provider.connection.getAccountInfo(vault));
+          assert.isAbove(await provider.connection.getBalance(newAuthority.publicKey), receiverBalanceBefore);
+        });
+
+        it("Sweeps and closes a project with a reward stream once every stake has left", async () => {
+          const { projectCount } = await program.account.platformConfig.fetch(platformConfigPda);
+          const projectIdBytes = projectCount.toBuffer('le', 8);
+          const [rewardProjectConfigPda] = anchor.web3.PublicKey.findProgramAddressSync(
+            [Buffer.from("project"), projectIdBytes],
+            program.programId
+          );
+          const [rewardProjectVaultPda] = anchor.web3.PublicKey.findProgramAddressSync(
+            [Buffer.from("vault"), projectIdBytes],
+            program.programId
+          );
+          const [rewardProjectVaultAuthorityPda] = anchor.web3.PublicKey.findProgramAddressSync(
+            [Buffer.from("vault-authority"), projectIdBytes],
+            program.programId
+          );
+          const [rewardVaultPda] = anchor.web3.PublicKey.findProgramAddressSync(
+            [Buffer.from("reward-vault"), projectIdBytes, Buffer.from([0])],
+            program.programId
+          );
+          const { mint: rewardMint, userTokenAccount: userRewardTokenAccount } = rewardStreams[0];
+
+          await program.methods
+            .registerProject("Reward Project", [{ durationDays: 0, multiplierBps: 10000 }])
+            .accountsStrict({
+              platformConfig: platformConfigPda,
+              projectConfig: rewardProjectConfigPda,
+              tokenMint: tokenMint,
+              vault: rewardProjectVaultPda,
+              vaultAuthority: rewardProjectVaultAuthorityPda,
+              authority: authority,
+              systemProgram: anchor.web3.SystemProgram.programId,
+              tokenProgram: tokenProgram,
+              rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+            })
+            .rpc();
+          await program.methods
+            .addRewardStream()
+            .accountsStrict({
+              platformConfig: platformConfigPda,
+              projectConfig: rewardProjectConfigPda,
+              rewardMint: rewardMint,
+              rewardVault: rewardVaultPda,
+              vaultAuthority: rewardProjectVaultAuthorityPda,
+              authority: authority,
+              systemProgram: anchor.web3.SystemProgram.programId,
+              rewardTokenProgram: tokenProgram,
+              rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+            })
+            .rpc();
+
+          // Fund an hour of rewards that nobody will stay around to earn
+          const funding = new anchor.BN(10 * 10 ** 9);
+          const funderTokenAccount = await getOrCreateAssociatedTokenAccount(
+            provider.connection,
+            (provider.wallet as any).payer,
+            rewardMint,
+            authority,
+            false,
+            undefined,
+            undefined,
+            tokenProgram
+          );
+          await mintTo(
+            provider.connection,
+            (provider.wallet as any).payer,
+            rewardMint,
+            funderTokenAccount.address,
+            authority,
+            funding.toNumber(),
+            [],
+            undefined,
+            tokenProgram
+          );
+          const now = Math.floor(Date.now() / 1000);
+          await program.methods
+            .fundRewards(0, funding, new anchor.BN(now - 60), new anchor.BN(now + 60 * 60))
+            .accountsStrict({
+              platformConfig: platformConfigPda,
+              projectConfig: rewardProjectConfigPda,
+              rewardVault: rewardVaultPda,
+              rewardMint: rewardMint,
+              funderTokenAccount: funderTokenAccount.address,
+              authority: authority,
+              rewardTokenProgram: tokenProgram,
+            })
+            .rpc();
+          await program.methods
+            .updateRewardRate(0, new anchor.BN(1000))
+            .accountsStrict({
+              platformConfig: platformConfigPda,
+              projectConfig: rewardProjectConfigPda,
+              rewardVault: rewardVaultPda,
+              authority: authority,
+            })
+            .rpc();
+
+          const stakeId = new anchor.BN(27 + suiteIndex * 100);
+          const [stakeInfoPda] = anchor.web3.PublicKey.findProgramAddressSync(
+            [Buffer.from("stake"), rewardProjectConfigPda.toBuffer(), user.publicKey.toBuffer(), stakeId.toBuffer('le', 8)],
+            program.programId
+          );
+          const [unstakeInfoPda] = anchor.web3.PublicKey.findProgramAddressSync(
+            [Buffer.from("unstake"), stakeInfoPda.toBuffer()],
+            program.programId
+          );
+          await (program.methods.stake as any)(new anchor.BN(10 * 10 ** 9), 0, stakeId, false)
+            .accounts({
+              projectConfig: rewardProjectConfigPda,
+              stakeInfo: stakeInfoPda,
+              unstakeInfo: unstakeInfoPda,
+              user: user.publicKey,
+              userTokenAccount: userTokenAccount,
+              vault: rewardProjectVaultPda,
+              systemProgram: anchor.web3.SystemProgram.programId,
+              tokenProgram: tokenProgram,
+            })
+            .signers([user])
+            .rpc();
+          await sleep(2000);
+          await program.methods.unstake(stakeId)
+            .accounts({
+              projectConfig: rewardProjectConfigPda,
+              stakeInfo: stakeInfoPda,
+              unstakeInfo: unstakeInfoPda,
+              user: user.publicKey,
+              userTokenAccount: userTokenAccount,
+              tokenMint: tokenMint,
+              vault: rewardProjectVaultPda,
+              vaultAuthority: rewardProjectVaultAuthorityPda,
+              feeWallet: feeWalletTokenAccount,
+              tokenProgram: tokenProgram,
+              systemProgram: anchor.web3.SystemProgram.programId,
+            })
+            .remainingAccounts([
+              { pubkey: rewardVaultPda, isWritable: true, isSigner: false },
+              { pubkey: userRewardTokenAccount, isWritable: true, isSigner: false },
+              { pubkey: rewardMint, isWritable: false, isSigner: false },
+              { pubkey: tokenProgram, isWritable: false, isSigner: false },
+            ])
+            .signers([user])
+            .rpc();
+
+          // With every stake gone, the rest of the schedule and the rounding dust are swept too
+          await program.methods
+            .sweepRewardSurplus(0)
+            .accountsStrict({
+              platformConfig: platformConfigPda,
+              projectConfig: rewardProjectConfigPda,
+              rewardVault: rewardVaultPda,
+              rewardMint: rewardMint,
+              vaultAuthority: rewardProjectVaultAuthorityPda,
+              to: funderTokenAccount.address,
+              authority: authority,
+              rewardTokenProgram: tokenProgram,
+            })
+            .rpc();
+          const rewardVaultAccount = await getAccount(provider.connection, rewardVaultPda, undefined, tokenProgram);
+          assert.equal(rewardVaultAccount.amount.toString(), "0");
+
+          await closeProject(rewardProjectConfigPda, authority, [
+            { pubkey: rewardVaultPda, isWritable: true, isSigner: false },
+            { pubkey: tokenProgram, isWritable: false, isSigner: false },
+          ]);
+
+          assert.isNull(await provider.connection.getAccountInfo(rewardProjectConfigPda));
+          assert.isNull(await provider.connection.getAccountInfo(rewardProjectVaultPda));
+          assert.isNull(await provider.connection.getAccountInfo(rewardVaultPda));
+        });
+      });
+
+      describe("Sweep surplus", () => {
+        const sweepSurplus = () =>
+          program.methods
+            .sweepSurplus()
+            .accountsStrict({
+              platformConfig: platformConfigPda,
+              projectConfig: projectConfigPda,
+              tokenMint: tokenMint,
+              vault: vaultPda,
+              vaultAuthority: vaultAuthorityPda,
+              to: feeWalletTokenAccount,
+              authority: authority,
+              tokenProgram: tokenProgram,
+            })
+            .rpc();
+
+        it("Sweeps only tokens above the staked total", async () => {
+          const surplus = 5 * 10 ** 9;
+          await mintTo(
+            provider.connection,
+            (provider.wallet as any).payer,
+            tokenMint,
+            vaultPda,
+            authority,
+            surplus,
+            [],
+            undefined,
+            tokenProgram
+          );
+          const { totalStaked } = await program.account.projectConfig.fetch(projectConfigPda);
+          const vaultBefore = await getAccount(provider.connection, vaultPda, undefined, tokenProgram);
+          const feeWalletBefore = await getAccount(provider.connection, feeWalletTokenAccount, undefined, tokenProgram);
+          const expected = Number(vaultBefore.amount) - totalStaked.toNumber();
+          assert.isAtLeast(expected, surplus);
+
+          await sweepSurplus();
+
+          const vaultAfter = await getAccount(provider.connection, vaultPda, undefined, tokenProgram);
+          const feeWalletAfter = await getAccount(provider.connection, feeWalletTokenAccount, undefined, tokenProgram);
+          assert.equal(Number(vaultAfter.amount), totalStaked.toNumber());
+          assert.equal(Number(feeWalletAfter.amount) - Number(feeWalletBefore.amount), expected);
+        });
+
+        it("Fails when there is no surplus", async () => {
+          try {
+            await sweepSurplus();
+            assert.fail("Sweeping an exact vault should have failed.");
+          } catch (error) {
+            assert.include(error.toString(), "NoSurplus");
+          }
+        });
+
+        it("Sweeps only reward vault tokens above the reserved rewards", async () => {
+          const stream = rewardStreams[0];
+          const surplus = 5 * 10 ** 9;
+          await mintTo(
+            provider.connection,
+            (provider.wallet as any).payer,
+            stream.mint,
+            stream.vault,
+            authority,
+            surplus,
+            [],
+            undefined,
+            tokenProgram
+          );
+          const to = await getOrCreateAssociatedTokenAccount(
+            provider.connection,
+            (provider.wallet as any).payer,
+            stream.mint,
+            authority,
+            false,
+            undefined,
+            undefined,
+            tokenProgram
+          );
+          const toBefore = await getAccount(provider.connection, to.address, undefined, tokenProgram);
+
+          await program.methods
+            .sweepRewardSurplus(0)
+            .accountsStrict({
+              platformConfig: platformConfigPda,
+              projectConfig: projectConfigPda,
+              rewardVault: stream.vault,
+              rewardMint: stream.mint,
+              vaultAuthority: vaultAuthorityPda,
+              to: to.address,
+              authority: authority,
+              rewardTokenProgram: tokenProgram,
+            })
+            .rpc();
+
+          // Whatever was swept, the vault still covers the accrued rewards and the rest of the schedule
+          const projectConfig = await program.account.projectConfig.fetch(projectConfigPda);
+          const rewardStream = projectConfig.rewardStreams[0];
+          const now = Math.floor(Date.now() / 1000);
+          const remainingEmission = rewardStream.rewardRate.toNumber() * Math.max(rewardStream.endTimestamp.toNumber() - now, 0);
+          const outstanding = rewardStream.totalDistributed.sub(rewardStream.totalClaimed).toNumber();
+          const vaultAfter = await getAccount(provider.connection, stream.vault, undefined, tokenProgram);
+          const toAfter = await getAccount(provider.connection, to.address, undefined, tokenProgram);
+          assert.isAtLeast(Number(toAfter.amount) - Number(toBefore.amount), surplus);
+          assert.isAtLeast(Number(vaultAfter.amount), outstanding + remainingEmission);
+        });
+      });
+
+      describe("Close user project", () => {
+        const closeUserProject = (projectConfig: anchor.web3.PublicKey) =>
+          program.methods
+            .closeUserProject()
+            .accountsStrict({
+              projectConfig: projectConfig,
+              userProjectInfo: anchor.web3.PublicKey.findProgramAddressSync(
+                [Buffer.from("user-project"), projectConfig.toBuffer(), user.publicKey.toBuffer()],
+                program.programId
+              )[0],
+              user: user.publicKey,
+            })
+            .signers([user])
+            .rpc();
+
+        it("Fails while the user still has tokens staked", async () => {
+          try {
+            await closeUserProject(projectConfigPda);
+            assert.fail("Closing the user's project account with stakes should have failed.");
+          } catch (error) {
+            assert.include(error.toString(), "UserProjectNotEmpty");
+          }
+        });
+
+        it("Closes the account once nothing is staked", async () => {
+          // The only stake in this project is cooling, so it no longer counts
+          await closeUserProject(coolingProjectConfigPda);
+
+          const [userProjectPda] = anchor.web3.PublicKey.findProgramAddressSync(
+            [Buffer.from("user-project"), coolingProjectConfigPda.toBuffer(), user.publicKey.toBuffer()],
+            program.programId
+          );
+          assert.isNull(await provider.connection.getAccountInfo(userProjectPda));
+        });
+      });
+      });
     });
   });
 });